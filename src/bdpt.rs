@@ -0,0 +1,531 @@
+use std::f64::consts::PI;
+
+use crate::integrator::{
+    environment_light, environment_shadow_light, punctual_light, survival_probability, Film,
+    Integrator, PathDepths,
+};
+use crate::materials::microfacet::{sample_cosine_hemisphere, Frame};
+use crate::prelude::*;
+use crate::scene::Scene;
+use crate::stats::{count, Counter};
+
+use rand::{thread_rng, Rng};
+
+/// Bidirectional path tracing: a path is traced from the camera and another
+/// from one of the emissive objects, and every vertex of one is joined to
+/// every vertex of the other. Each way of building a path is weighed against
+/// the others by how likely it was to find it, so light focused through glass
+/// or squeezed through a gap, which camera paths almost never find, is picked
+/// up by the light paths instead. Light paths that reach the camera directly
+/// land on whichever pixel they pass through.
+///
+/// Only emissive spheres start light paths; other lights and the environment
+/// are gathered along the camera path as in `PathTracer`. Paths are traced in
+/// RGB, and only `depths.max` and `depths.roulette` apply.
+#[derive(Debug)]
+pub struct Bidirectional;
+
+impl Integrator for Bidirectional {
+    fn colour(&self, ray: &Ray, scene: &Scene, depths: &PathDepths, film: &mut Film) -> Colour {
+        let ray = Ray::new(ray.origin(), ray.direction());
+        let mut radiance = Colour::default();
+
+        let mut camera_path = vec![Vertex::camera(ray.clone())];
+        let pdf_direction = film.pdf_direction(&ray.direction());
+        if let Some((escaped, beta, pdf)) = random_walk(
+            scene,
+            ray.clone(),
+            Colour::new(1, 1, 1),
+            pdf_direction,
+            depths.max + 1,
+            depths.roulette,
+            &mut camera_path,
+        ) {
+            // Camera rays see the environment directly; later ones weigh it
+            // against the shadow rays aimed at it.
+            let scatter_pdf = if camera_path.len() > 1 { pdf } else { 0. };
+            radiance += beta * environment_light(scene, &escaped, scatter_pdf);
+        }
+        match camera_path.get(1) {
+            Some(first) => film.record_hit(&first.rec),
+            None => film.record_miss(scene, &ray),
+        }
+        for vertex in camera_path.iter().skip(1).take(depths.max as usize) {
+            if vertex.delta {
+                continue;
+            }
+            let Some(mat) = vertex.rec.material.as_ref() else {
+                continue;
+            };
+            radiance += vertex.beta
+                * (punctual_light(scene, &vertex.ray, mat.as_ref(), &vertex.rec)
+                    + environment_shadow_light(scene, &vertex.ray, mat.as_ref(), &vertex.rec));
+        }
+
+        let light_path = light_subpath(scene, depths);
+
+        for t in 1..=camera_path.len() {
+            for s in 0..=light_path.len() {
+                if s + t < 2 || (s == 1 && t == 1) || s + t - 2 > depths.max as usize {
+                    continue;
+                }
+                match t {
+                    1 => connect_to_camera(scene, film, &light_path, s),
+                    _ => radiance += connect(scene, film, &camera_path, &light_path, s, t),
+                }
+            }
+        }
+        radiance
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum VertexKind {
+    Camera,
+    Light,
+    Surface,
+}
+
+/// A point on a camera or light path.
+#[derive(Clone)]
+struct Vertex {
+    kind: VertexKind,
+    /// The surface at the vertex, facing the ray that arrived there. Only the
+    /// point is set for the camera.
+    rec: Hit,
+    /// The ray that arrived at the vertex, for looking up the material.
+    ray: Ray,
+    /// The light or importance carried to the vertex, divided by the pdf of
+    /// the path so far.
+    beta: Colour,
+    /// Whether the vertex scattered off a perfectly smooth surface, so nothing
+    /// can be joined to it.
+    delta: bool,
+    /// The pdf per unit area of the path reaching the vertex.
+    pdf_fwd: f64,
+    /// The pdf per unit area of a path traced the other way reaching it.
+    pdf_rev: f64,
+}
+
+impl Vertex {
+    fn camera(ray: Ray) -> Self {
+        Self {
+            kind: VertexKind::Camera,
+            rec: Hit {
+                p: ray.origin(),
+                ..Default::default()
+            },
+            ray,
+            beta: Colour::new(1, 1, 1),
+            delta: false,
+            pdf_fwd: 1.,
+            pdf_rev: 0.,
+        }
+    }
+
+    fn light(rec: Hit, beta: Colour, pdf_fwd: f64) -> Self {
+        Self {
+            kind: VertexKind::Light,
+            rec,
+            ray: Default::default(),
+            beta,
+            delta: false,
+            pdf_fwd,
+            pdf_rev: 0.,
+        }
+    }
+
+    fn p(&self) -> Point3 {
+        self.rec.p
+    }
+
+    /// Turns a pdf per unit solid angle of leaving this vertex towards `next`
+    /// into one per unit area at `next`.
+    fn convert_density(&self, pdf: f64, next: &Vertex) -> f64 {
+        let offset = next.p() - self.p();
+        let distance_squared = offset.length_squared();
+        if distance_squared == 0. {
+            return 0.;
+        }
+        match next.kind {
+            VertexKind::Camera => pdf / distance_squared,
+            VertexKind::Light | VertexKind::Surface => {
+                pdf * next.rec.normal.dot(&offset.unit_vector()).abs() / distance_squared
+            }
+        }
+    }
+
+    /// How much of the light arriving from `point` the surface sends back
+    /// along the ray that reached it, including the cosine term.
+    fn f(&self, point: &Point3) -> Colour {
+        match self.rec.material.as_ref() {
+            Some(mat) => mat.bsdf(&self.ray, &self.rec, &(point - self.p()).unit_vector()),
+            None => Colour::default(),
+        }
+    }
+
+    /// The pdf per unit area of a path arriving from `prev` carrying on to
+    /// `next`.
+    fn pdf(&self, film: &Film, prev: Option<&Vertex>, next: &Vertex) -> f64 {
+        let direction = next.p() - self.p();
+        match self.kind {
+            VertexKind::Camera => self.convert_density(film.pdf_direction(&direction), next),
+            VertexKind::Light => self.pdf_light(next),
+            VertexKind::Surface => {
+                let (Some(prev), Some(mat)) = (prev, self.rec.material.as_ref()) else {
+                    return 0.;
+                };
+                let ray = Ray::new(prev.p(), self.p() - prev.p());
+                let pdf = mat.pdf(&ray, &facing(&self.rec, &ray), &direction.unit_vector());
+                self.convert_density(pdf, next)
+            }
+        }
+    }
+
+    /// The pdf per unit area of light leaving this point on an emitter
+    /// reaching `next`.
+    fn pdf_light(&self, next: &Vertex) -> f64 {
+        let direction = (next.p() - self.p()).unit_vector();
+        let cos_theta = outward_normal(&self.rec).dot(&direction).max(0.);
+        self.convert_density(cos_theta / PI, next)
+    }
+}
+
+/// Starts a path on one of the emissive objects.
+fn light_subpath(scene: &Scene, depths: &PathDepths) -> Vec<Vertex> {
+    let Some(sample) = scene.emitters().sample_emission() else {
+        return vec![];
+    };
+    let Some(mat) = sample.rec.material.as_ref() else {
+        return vec![];
+    };
+    let emitted = mat.emitted(&sample.rec);
+    if emitted.near_zero() || sample.pdf <= 0. {
+        return vec![];
+    }
+
+    let mut rng = thread_rng();
+    let local = sample_cosine_hemisphere(rng.gen(), rng.gen());
+    if local.z() <= 0. {
+        return vec![];
+    }
+    let direction = Frame::from_normal(&sample.rec.normal).to_world(&local);
+    let ray = Ray::new(sample.rec.p, direction);
+
+    let mut path = vec![Vertex::light(sample.rec, emitted / sample.pdf, sample.pdf)];
+    random_walk(
+        scene,
+        ray,
+        emitted * PI / sample.pdf,
+        local.z() / PI,
+        depths.max,
+        depths.roulette,
+        &mut path,
+    );
+    path
+}
+
+/// Extends `path` by following `ray` for up to `max_bounces` hits, filling in
+/// the pdfs of each vertex both ways. `pdf` is the pdf per unit solid angle of
+/// `ray` having been chosen. Returns the ray, weight and pdf of a path that
+/// left the scene.
+fn random_walk(
+    scene: &Scene,
+    ray: Ray,
+    beta: Colour,
+    pdf: f64,
+    max_bounces: u32,
+    roulette: u32,
+    path: &mut Vec<Vertex>,
+) -> Option<(Ray, Colour, f64)> {
+    let (mut ray, mut beta, mut pdf_fwd) = (ray, beta, pdf);
+    let start = beta.max_component();
+
+    for bounce in 0..max_bounces {
+        let mut rec: Hit = Default::default();
+        if !scene.hit(&ray, 0.0001, f64::INFINITY, &mut rec) {
+            return Some((ray, beta, pdf_fwd));
+        }
+        let mat = rec.material.clone()?;
+        let previous = path.last()?;
+        let mut vertex = Vertex {
+            kind: VertexKind::Surface,
+            rec,
+            ray: ray.clone(),
+            beta,
+            delta: false,
+            pdf_fwd: 0.,
+            pdf_rev: 0.,
+        };
+        vertex.pdf_fwd = previous.convert_density(pdf_fwd, &vertex);
+        path.push(vertex);
+        if bounce + 1 >= max_bounces {
+            return None;
+        }
+
+        let vertex = path.last_mut()?;
+        let ScatterResult::Scattered {
+            attenuation,
+            scattered,
+            delta,
+            ..
+        } = mat.scatter(&ray, &vertex.rec)
+        else {
+            return None;
+        };
+        count(Counter::BounceRays);
+        let direction = scattered.direction().unit_vector();
+        pdf_fwd = match delta {
+            true => 0.,
+            false => mat.pdf(&ray, &vertex.rec, &direction),
+        };
+        let reversed = Ray::new(vertex.rec.p + direction, -direction);
+        let mut pdf_rev = mat.pdf(
+            &reversed,
+            &facing(&vertex.rec, &reversed),
+            &-ray.direction().unit_vector(),
+        );
+        if pdf_fwd == 0. {
+            vertex.delta = true;
+            pdf_rev = 0.;
+        }
+        beta *= attenuation;
+
+        let len = path.len();
+        path[len - 2].pdf_rev = path[len - 1].convert_density(pdf_rev, &path[len - 2]);
+        ray = scattered;
+
+        if bounce + 1 >= roulette {
+            match survival_probability(beta.max_component() / start) {
+                Some(survival) => beta /= survival,
+                None => return None,
+            }
+        }
+    }
+    None
+}
+
+/// Joins the first `s` vertices of the light path to the first `t` of the
+/// camera path, for `t` of at least two.
+fn connect(
+    scene: &Scene,
+    film: &Film,
+    camera_path: &[Vertex],
+    light_path: &[Vertex],
+    s: usize,
+    t: usize,
+) -> Colour {
+    let pt = &camera_path[t - 1];
+    match s {
+        // The camera path found an emitter by itself.
+        0 => {
+            let Some(mat) = pt.rec.material.as_ref() else {
+                return Colour::default();
+            };
+            let emitted = mat.emitted(&pt.rec);
+            if emitted.near_zero() {
+                return Colour::default();
+            }
+            let weight = match scene.emitters().emission_pdf(&pt.p()) > 0. {
+                true => mis_weight(scene, film, camera_path, light_path, s, t, None),
+                false => 1.,
+            };
+            pt.beta * emitted * weight
+        }
+        // A fresh point on an emitter, picked for how much light it sends to
+        // the end of the camera path.
+        1 => {
+            if pt.delta {
+                return Colour::default();
+            }
+            let Some(sample) = scene.emitters().sample(&pt.p()) else {
+                return Colour::default();
+            };
+            let response = pt.f(&(pt.p() + sample.direction));
+            if response.near_zero() || sample.distance <= 0.0001 {
+                return Colour::default();
+            }
+            count(Counter::ShadowRays);
+            let shadow_ray = Ray::new(pt.p(), sample.direction);
+            let mut rec: Hit = Default::default();
+            if !scene.hit(&shadow_ray, 0.0001, sample.distance + 0.0001, &mut rec)
+                || rec.t < sample.distance - 0.0001
+            {
+                return Colour::default();
+            }
+            let pdf_fwd = scene.emitters().emission_pdf(&rec.p);
+            let sampled = Vertex::light(rec, sample.radiance / sample.pdf, pdf_fwd);
+            let weight = mis_weight(scene, film, camera_path, light_path, s, t, Some(&sampled));
+            pt.beta * response * sampled.beta * weight
+        }
+        _ => {
+            let qs = &light_path[s - 1];
+            if pt.delta || qs.delta {
+                return Colour::default();
+            }
+            let distance_squared = (pt.p() - qs.p()).length_squared();
+            let contribution = qs.beta * qs.f(&pt.p()) * pt.f(&qs.p()) * pt.beta / distance_squared;
+            if contribution.near_zero() || !visible(scene, &qs.p(), &pt.p()) {
+                return Colour::default();
+            }
+            contribution * mis_weight(scene, film, camera_path, light_path, s, t, None)
+        }
+    }
+}
+
+/// Joins the first `s` vertices of the light path to a point on the lens,
+/// adding the light to whichever pixel it lands on.
+fn connect_to_camera(scene: &Scene, film: &mut Film, light_path: &[Vertex], s: usize) {
+    let qs = &light_path[s - 1];
+    if qs.delta {
+        return;
+    }
+    let camera = film.camera();
+    let lens_point = camera.sample_lens();
+    let direction = qs.p() - lens_point;
+    let Some((x, y)) = film.pixel(&lens_point, &direction) else {
+        return;
+    };
+    let importance = film.importance(&direction);
+    let cos_theta = camera.cos_theta(&direction);
+    let lens_area = camera.lens_area();
+    let contribution = qs.beta * qs.f(&lens_point) * importance * cos_theta * lens_area
+        / direction.length_squared();
+    if contribution.near_zero() || !visible(scene, &qs.p(), &lens_point) {
+        return;
+    }
+    let sampled = Vertex::camera(Ray::new(lens_point, direction));
+    let weight = mis_weight(scene, film, &[], light_path, s, 1, Some(&sampled));
+    film.splat(x, y, contribution * weight);
+}
+
+/// The pdfs that decide how much a vertex counts for under each strategy.
+#[derive(Clone, Copy)]
+struct Density {
+    fwd: f64,
+    rev: f64,
+    delta: bool,
+}
+
+impl From<&Vertex> for Density {
+    fn from(vertex: &Vertex) -> Self {
+        Self {
+            fwd: vertex.pdf_fwd,
+            rev: vertex.pdf_rev,
+            delta: vertex.delta,
+        }
+    }
+}
+
+/// The balance heuristic weight of joining `s` light vertices to `t` camera
+/// vertices, against every other split of the same path. `sampled` stands in
+/// for the last light vertex when `s` is 1, or the camera vertex when `t` is
+/// 1, since those are picked afresh for the connection.
+fn mis_weight(
+    scene: &Scene,
+    film: &Film,
+    camera_path: &[Vertex],
+    light_path: &[Vertex],
+    s: usize,
+    t: usize,
+    sampled: Option<&Vertex>,
+) -> f64 {
+    if s + t == 2 {
+        return 1.;
+    }
+
+    let pt = match (t, sampled) {
+        (1, Some(sampled)) => sampled,
+        _ => &camera_path[t - 1],
+    };
+    let qs = match (s, sampled) {
+        (0, _) => None,
+        (1, Some(sampled)) => Some(sampled),
+        _ => Some(&light_path[s - 1]),
+    };
+    let pt_minus = (t >= 2).then(|| &camera_path[t - 2]);
+    let qs_minus = (s >= 2).then(|| &light_path[s - 2]);
+
+    let mut camera: Vec<Density> = camera_path.iter().take(t).map(Density::from).collect();
+    if t == 1 {
+        camera = vec![Density::from(pt)];
+    }
+    let mut light: Vec<Density> = light_path.iter().take(s).map(Density::from).collect();
+    if let (1, Some(qs)) = (s, qs) {
+        light = vec![Density::from(qs)];
+    }
+
+    camera[t - 1].delta = false;
+    camera[t - 1].rev = match qs {
+        Some(qs) => qs.pdf(film, qs_minus, pt),
+        None => scene.emitters().emission_pdf(&pt.p()),
+    };
+    if let Some(pt_minus) = pt_minus {
+        camera[t - 2].rev = match qs {
+            Some(qs) => pt.pdf(film, Some(qs), pt_minus),
+            None => pt.pdf_light(pt_minus),
+        };
+    }
+    if let Some(qs) = qs {
+        light[s - 1].delta = false;
+        light[s - 1].rev = pt.pdf(film, pt_minus, qs);
+    }
+    if let (Some(qs), Some(qs_minus)) = (qs, qs_minus) {
+        light[s - 2].rev = qs.pdf(film, Some(pt), qs_minus);
+    }
+
+    // Smooth surfaces have no pdf; they cancel out of the ratios.
+    let remap = |pdf: f64| if pdf != 0. { pdf } else { 1. };
+    let mut sum = 0.;
+    let mut ratio = 1.;
+    for i in (1..t).rev() {
+        ratio *= remap(camera[i].rev) / remap(camera[i].fwd);
+        if !camera[i].delta && !camera[i - 1].delta {
+            sum += ratio;
+        }
+    }
+    ratio = 1.;
+    for i in (0..s).rev() {
+        ratio *= remap(light[i].rev) / remap(light[i].fwd);
+        let previous_delta = i > 0 && light[i - 1].delta;
+        if !light[i].delta && !previous_delta {
+            sum += ratio;
+        }
+    }
+    1. / (1. + sum)
+}
+
+fn visible(scene: &Scene, from: &Point3, to: &Point3) -> bool {
+    count(Counter::ShadowRays);
+    let offset = to - from;
+    let distance = offset.length();
+    let mut rec: Hit = Default::default();
+    !scene.hit(
+        &Ray::new(*from, offset / distance),
+        0.0001,
+        distance - 0.0001,
+        &mut rec,
+    )
+}
+
+fn outward_normal(rec: &Hit) -> Vector3 {
+    match rec.front_face {
+        true => rec.normal,
+        false => -rec.normal,
+    }
+}
+
+/// The hit as a ray arriving along `ray` would have found it, with the normal
+/// turned to face the ray.
+fn facing(rec: &Hit, ray: &Ray) -> Hit {
+    let outward = outward_normal(rec);
+    let front_face = ray.direction().dot(&outward) < 0.;
+    Hit {
+        normal: match front_face {
+            true => outward,
+            false => -outward,
+        },
+        front_face,
+        ..rec.clone()
+    }
+}
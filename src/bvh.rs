@@ -0,0 +1,251 @@
+use crate::prelude::*;
+use crate::stats::{count, Counter};
+
+/// Padding added to flat boxes, so that a ray can still hit a box around an
+/// axis-aligned triangle.
+const FLAT_PADDING: f64 = 1e-6;
+/// Deeper than any tree built by halving, so traversal never runs out of stack.
+const MAX_DEPTH: usize = 64;
+
+fn axis_value(point: &Point3, axis: usize) -> f64 {
+    match axis {
+        0 => point.x(),
+        1 => point.y(),
+        _ => point.z(),
+    }
+}
+
+/// An axis-aligned box around an object.
+#[derive(Clone, Copy, Debug)]
+pub struct Aabb {
+    pub min: Point3,
+    pub max: Point3,
+}
+
+impl Aabb {
+    /// The box spanning two corners, padded where it would be flat.
+    pub fn new(a: Point3, b: Point3) -> Self {
+        let padded = |axis: usize| {
+            let (a, b) = (axis_value(&a, axis), axis_value(&b, axis));
+            match (a - b).abs() < FLAT_PADDING {
+                true => (a.min(b) - FLAT_PADDING, a.max(b) + FLAT_PADDING),
+                false => (a.min(b), a.max(b)),
+            }
+        };
+        let (x, y, z) = (padded(0), padded(1), padded(2));
+        Self {
+            min: Point3::new(x.0, y.0, z.0),
+            max: Point3::new(x.1, y.1, z.1),
+        }
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min: Point3::new(
+                self.min.x().min(other.min.x()),
+                self.min.y().min(other.min.y()),
+                self.min.z().min(other.min.z()),
+            ),
+            max: Point3::new(
+                self.max.x().max(other.max.x()),
+                self.max.y().max(other.max.y()),
+                self.max.z().max(other.max.z()),
+            ),
+        }
+    }
+
+    fn centre(&self) -> Point3 {
+        0.5 * (self.min + self.max)
+    }
+
+    /// Whether the ray passes through the box between `t_min` and `t_max`,
+    /// using the slab test.
+    fn hit(&self, ray: &Ray, mut t_min: f64, mut t_max: f64) -> bool {
+        for axis in 0..3 {
+            let origin = axis_value(&ray.origin(), axis);
+            let inverse = 1. / axis_value(&ray.direction(), axis);
+            let mut t0 = (axis_value(&self.min, axis) - origin) * inverse;
+            let mut t1 = (axis_value(&self.max, axis) - origin) * inverse;
+            if inverse < 0. {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            // NaNs from a ray lying in the plane of a face leave the range be.
+            t_min = t0.max(t_min);
+            t_max = t1.min(t_max);
+            if t_max < t_min {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[derive(Debug)]
+struct Node {
+    bounds: Aabb,
+    kind: NodeKind,
+}
+
+#[derive(Debug)]
+enum NodeKind {
+    Leaf(usize),
+    /// The children, split along `axis` with the left one lower.
+    Interior {
+        left: usize,
+        right: usize,
+        axis: usize,
+    },
+}
+
+/// A bounding volume hierarchy over the scene's objects, so that a ray is only
+/// tested against the few objects near its path. Objects without a bounding
+/// box are tested by every ray.
+#[derive(Debug, Default)]
+pub struct Bvh {
+    nodes: Vec<Node>,
+    unbounded: Vec<usize>,
+}
+
+impl Bvh {
+    /// Builds the tree over `world`, whose objects it refers to by index.
+    pub fn new(world: &[Box<dyn Hittable>]) -> Self {
+        let mut bvh = Self::default();
+        let mut bounded = vec![];
+        for (index, object) in world.iter().enumerate() {
+            match object.bounding_box() {
+                Some(bounds) => bounded.push((index, bounds)),
+                None => bvh.unbounded.push(index),
+            }
+        }
+        if !bounded.is_empty() {
+            bvh.build(&mut bounded);
+        }
+        bvh
+    }
+
+    /// Adds the nodes for a set of objects, splitting them in half along the
+    /// axis their centres spread furthest, and returns the index of the top.
+    fn build(&mut self, objects: &mut [(usize, Aabb)]) -> usize {
+        if let [(index, bounds)] = objects {
+            self.nodes.push(Node {
+                bounds: *bounds,
+                kind: NodeKind::Leaf(*index),
+            });
+            return self.nodes.len() - 1;
+        }
+
+        let spread = |axis: usize| {
+            let values = objects
+                .iter()
+                .map(|(_, bounds)| axis_value(&bounds.centre(), axis));
+            values.clone().fold(f64::MIN, f64::max) - values.fold(f64::MAX, f64::min)
+        };
+        let axis = (0..3)
+            .max_by(|a, b| spread(*a).total_cmp(&spread(*b)))
+            .unwrap_or_default();
+        objects.sort_by(|(_, a), (_, b)| {
+            axis_value(&a.centre(), axis).total_cmp(&axis_value(&b.centre(), axis))
+        });
+
+        let (left_objects, right_objects) = objects.split_at_mut(objects.len() / 2);
+        let left = self.build(left_objects);
+        let right = self.build(right_objects);
+        self.nodes.push(Node {
+            bounds: self.nodes[left].bounds.union(&self.nodes[right].bounds),
+            kind: NodeKind::Interior { left, right, axis },
+        });
+        self.nodes.len() - 1
+    }
+
+    /// Finds the closest hit among `world`'s objects, which must be the ones
+    /// the tree was built over.
+    pub fn hit(
+        &self,
+        world: &[Box<dyn Hittable>],
+        ray: &Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut Hit,
+    ) -> bool {
+        let mut hit_anything = false;
+        let mut closest_so_far = t_max;
+        for index in &self.unbounded {
+            if world[*index].hit(ray, t_min, closest_so_far, rec) {
+                hit_anything = true;
+                closest_so_far = rec.t;
+            }
+        }
+
+        let Some(root) = self.nodes.len().checked_sub(1) else {
+            return hit_anything;
+        };
+        let mut stack = [0; MAX_DEPTH];
+        stack[0] = root;
+        let mut depth = 1;
+        while depth > 0 {
+            depth -= 1;
+            let node = &self.nodes[stack[depth]];
+            count(Counter::BvhNodeVisits);
+            if !node.bounds.hit(ray, t_min, closest_so_far) {
+                continue;
+            }
+            match node.kind {
+                NodeKind::Leaf(index) => {
+                    if world[index].hit(ray, t_min, closest_so_far, rec) {
+                        hit_anything = true;
+                        closest_so_far = rec.t;
+                    }
+                }
+                NodeKind::Interior { left, right, axis } => {
+                    // Visit the nearer child first, so the further one can
+                    // often be skipped.
+                    let (near, far) = match axis_value(&ray.direction(), axis) < 0. {
+                        true => (right, left),
+                        false => (left, right),
+                    };
+                    stack[depth] = far;
+                    stack[depth + 1] = near;
+                    depth += 2;
+                }
+            }
+        }
+        hit_anything
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::build_cover;
+
+    use rand::{prelude::StdRng, Rng, SeedableRng};
+
+    #[test]
+    fn finds_the_same_hits_as_testing_every_object() {
+        let scene = build_cover();
+        let world = scene.world();
+        let bvh = Bvh::new(world);
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..2000 {
+            let origin = Point3::new(
+                rng.gen_range(-15.0..15.0),
+                rng.gen_range(0.1..4.0),
+                rng.gen_range(-15.0..15.0),
+            );
+            let direction = Vector3::new(
+                rng.gen_range(-1.0..1.0),
+                rng.gen_range(-1.0..1.0),
+                rng.gen_range(-1.0..1.0),
+            );
+            let ray = Ray::new(origin, direction);
+            let (mut expected, mut found): (Hit, Hit) = Default::default();
+            let hit = world
+                .as_ref()
+                .hit(&ray, 0.0001, f64::INFINITY, &mut expected);
+            assert_eq!(bvh.hit(world, &ray, 0.0001, f64::INFINITY, &mut found), hit);
+            if hit {
+                assert_eq!(found.t, expected.t);
+            }
+        }
+    }
+}
@@ -1,7 +1,79 @@
+use std::f64::consts::PI;
+
 use crate::prelude::*;
 
 use num_traits::NumCast;
 
+/// Where the camera is and how its lens is set up. Doubles as the builder for
+/// `Camera`, since the aspect ratio is only known once the image size is.
+#[derive(Clone, Copy, Debug)]
+pub struct CameraSettings {
+    pub look_from: Point3,
+    pub look_at: Point3,
+    pub up_vector: Vector3,
+    pub v_fov: f64,
+    pub aperture: f64,
+    pub focus_dist: f64,
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        Self {
+            look_from: Point3::new(13, 2, 3),
+            look_at: Point3::new(0, 0, 0),
+            up_vector: Vector3::new(0, 1, 0),
+            v_fov: 20.,
+            aperture: 0.1,
+            focus_dist: 10.,
+        }
+    }
+}
+
+impl CameraSettings {
+    pub fn look_from(mut self, look_from: Point3) -> Self {
+        self.look_from = look_from;
+        self
+    }
+
+    pub fn look_at(mut self, look_at: Point3) -> Self {
+        self.look_at = look_at;
+        self
+    }
+
+    pub fn up_vector(mut self, up_vector: Vector3) -> Self {
+        self.up_vector = up_vector;
+        self
+    }
+
+    /// Vertical field of view in degrees.
+    pub fn v_fov(mut self, v_fov: f64) -> Self {
+        self.v_fov = v_fov;
+        self
+    }
+
+    pub fn aperture(mut self, aperture: f64) -> Self {
+        self.aperture = aperture;
+        self
+    }
+
+    pub fn focus_dist(mut self, focus_dist: f64) -> Self {
+        self.focus_dist = focus_dist;
+        self
+    }
+
+    pub fn build(&self, aspect_ratio: f64) -> Camera {
+        Camera::new(
+            self.look_from,
+            self.look_at,
+            self.up_vector,
+            self.v_fov,
+            aspect_ratio,
+            self.aperture,
+            self.focus_dist,
+        )
+    }
+}
+
 pub struct Camera {
     origin: Point3,
     lower_left_corner: Point3,
@@ -9,10 +81,20 @@ pub struct Camera {
     vertical: Vector3,
     u: Vector3,
     v: Vector3,
+    /// The direction the camera looks in.
+    forward: Vector3,
+    /// The area `get_ray`'s unit square of image covers at unit distance.
+    film_area: f64,
     lens_radius: f64,
+    focus_dist: f64,
 }
 
 impl Camera {
+    /// Starts from the cover scene's viewpoint; finish with `build`.
+    pub fn builder() -> CameraSettings {
+        CameraSettings::default()
+    }
+
     pub fn new<T: NumCast>(
         look_from: Point3,
         look_at: Point3,
@@ -45,7 +127,10 @@ impl Camera {
             vertical,
             u,
             v,
+            forward: -w,
+            film_area: viewport_width * viewport_height,
             lens_radius,
+            focus_dist,
         }
     }
 
@@ -58,4 +143,67 @@ impl Camera {
             self.lower_left_corner + s * self.horizontal + t * self.vertical - self.origin - offset,
         )
     }
+
+    /// Whether the lens is a single point, so rays can't be aimed at it.
+    pub fn is_pinhole(&self) -> bool {
+        self.lens_radius <= 0.
+    }
+
+    /// A random point on the lens, for connecting light paths to the camera.
+    pub fn sample_lens(&self) -> Point3 {
+        let rd = self.lens_radius * Vector3::random_in_unit_disk();
+        self.origin + self.u * rd.x() + self.v * rd.y()
+    }
+
+    /// The area of the lens, or 1 for a pinhole so that it can stand in for a
+    /// pdf of choosing the point.
+    pub fn lens_area(&self) -> f64 {
+        match self.is_pinhole() {
+            true => 1.,
+            false => PI * self.lens_radius * self.lens_radius,
+        }
+    }
+
+    /// The cosine between `direction` and the way the camera faces.
+    pub fn cos_theta(&self, direction: &Vector3) -> f64 {
+        direction.unit_vector().dot(&self.forward)
+    }
+
+    /// Where a ray leaving `lens_point` along `direction` lands on the image,
+    /// in the coordinates `get_ray` takes, or `None` if it leaves the camera
+    /// backwards. The coordinates aren't clamped to the image.
+    pub fn raster_position(&self, lens_point: &Point3, direction: &Vector3) -> Option<(f64, f64)> {
+        let cos_theta = self.cos_theta(direction);
+        if cos_theta <= 0. {
+            return None;
+        }
+        let focus_point = lens_point + direction.unit_vector() * (self.focus_dist / cos_theta);
+        let offset = focus_point - self.lower_left_corner;
+        Some((
+            offset.dot(&self.horizontal) / self.horizontal.length_squared(),
+            offset.dot(&self.vertical) / self.vertical.length_squared(),
+        ))
+    }
+
+    /// How strongly the camera responds to light arriving back along
+    /// `direction`, for an image that covers `coverage` times `get_ray`'s unit
+    /// square. Integrated over the lens and every direction onto the image it
+    /// comes to one.
+    pub fn importance(&self, direction: &Vector3, coverage: f64) -> f64 {
+        let cos_theta = self.cos_theta(direction);
+        match cos_theta > 0. {
+            true => 1. / (coverage * self.film_area * self.lens_area() * cos_theta.powi(4)),
+            false => 0.,
+        }
+    }
+
+    /// The pdf per unit solid angle of `get_ray` choosing `direction`, for an
+    /// image that covers `coverage` times its unit square.
+    pub fn pdf_direction(&self, direction: &Vector3, coverage: f64) -> f64 {
+        let cos_theta = self.cos_theta(direction);
+        match cos_theta > 0. {
+            true => 1. / (coverage * self.film_area * cos_theta.powi(3)),
+            false => 0.,
+        }
+    }
 }
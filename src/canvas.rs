@@ -50,8 +50,12 @@ impl Canvas {
         );
     }
 
-    pub fn set_pixel(&mut self, x: u32, y: u32, colour: Colour, samples_per_pixel: u32) {
-        self.image
-            .set_pixel(x, y, colour.to_color(samples_per_pixel));
+    pub fn update(&mut self, pixels: &[Colour]) {
+        for (index, colour) in pixels.iter().enumerate() {
+            let x = index as u32 % self.width;
+            let y = index as u32 / self.width;
+            let (r, g, b) = colour.to_rgb8();
+            self.image.set_pixel(x, y, Color::from_rgba(r, g, b, 255));
+        }
     }
 }
@@ -0,0 +1,140 @@
+use std::fmt::Debug;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Read, Result, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::framebuffer::FrameBuffer;
+use crate::wire::*;
+
+const MAGIC: &[u8; 8] = b"RTIOWCKP";
+const VERSION: u32 = 4;
+
+/// A stable hash of a value's `Debug` representation, used to tell whether the
+/// scene or settings have changed since a checkpoint was written.
+pub fn fingerprint(value: &impl Debug) -> u64 {
+    format!("{:?}", value)
+        .bytes()
+        .fold(0xcbf29ce484222325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        })
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct CheckpointHeader {
+    pub width: u32,
+    pub height: u32,
+    pub scene_hash: u64,
+    pub settings_hash: u64,
+    pub seed: u64,
+}
+
+impl CheckpointHeader {
+    fn write_to(&self, out: &mut impl Write) -> Result<()> {
+        out.write_all(MAGIC)?;
+        write_u32(out, VERSION)?;
+        write_u32(out, self.width)?;
+        write_u32(out, self.height)?;
+        write_u64(out, self.scene_hash)?;
+        write_u64(out, self.settings_hash)?;
+        write_u64(out, self.seed)
+    }
+
+    fn read_from(input: &mut impl Read) -> Result<CheckpointHeader> {
+        let mut magic = [0u8; 8];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC || read_u32(input)? != VERSION {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "Not a checkpoint written by this version of rtiow",
+            ));
+        }
+        Ok(CheckpointHeader {
+            width: read_u32(input)?,
+            height: read_u32(input)?,
+            scene_hash: read_u64(input)?,
+            settings_hash: read_u64(input)?,
+            seed: read_u64(input)?,
+        })
+    }
+}
+
+/// Writes the checkpoint beside `path` first so a crash mid-write never
+/// destroys the previous one.
+pub fn write_checkpoint(
+    path: &Path,
+    header: &CheckpointHeader,
+    framebuffer: &FrameBuffer,
+) -> Result<()> {
+    let temp_path = path.with_extension("tmp");
+    {
+        let mut out = BufWriter::new(File::create(&temp_path)?);
+        header.write_to(&mut out)?;
+        framebuffer.write_to(&mut out)?;
+        out.flush()?;
+    }
+    fs::rename(temp_path, path)
+}
+
+/// Loads a checkpoint, refusing it unless it was written for `expected`.
+pub fn read_checkpoint(path: &Path, expected: &CheckpointHeader) -> Result<FrameBuffer> {
+    let mut input = BufReader::new(File::open(path)?);
+    let header = CheckpointHeader::read_from(&mut input)?;
+
+    let mismatch = if header.scene_hash != expected.scene_hash {
+        Some("the scene has changed")
+    } else if (header.width, header.height) != (expected.width, expected.height) {
+        Some("the resolution has changed")
+    } else if header.settings_hash != expected.settings_hash || header.seed != expected.seed {
+        Some("the render settings have changed")
+    } else {
+        None
+    };
+    if let Some(reason) = mismatch {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("Refusing to resume from {}: {}", path.display(), reason),
+        ));
+    }
+
+    FrameBuffer::read_from(&mut input, header.width, header.height)
+}
+
+/// Writes a checkpoint whenever `interval` has passed since the last one.
+pub struct Checkpointer {
+    path: PathBuf,
+    header: CheckpointHeader,
+    interval: Duration,
+    last_written: Instant,
+}
+
+impl Checkpointer {
+    pub fn new(path: PathBuf, header: CheckpointHeader, interval: Duration) -> Self {
+        Self {
+            path,
+            header,
+            interval,
+            last_written: Instant::now(),
+        }
+    }
+
+    pub fn tick(&mut self, framebuffer_ref: &Mutex<FrameBuffer>) -> Result<()> {
+        if self.last_written.elapsed() < self.interval {
+            return Ok(());
+        }
+        self.write(framebuffer_ref)
+    }
+
+    /// Writes a copy of the framebuffer, so the render threads can carry on
+    /// while the file is written.
+    pub fn write(&mut self, framebuffer_ref: &Mutex<FrameBuffer>) -> Result<()> {
+        let framebuffer = framebuffer_ref
+            .lock()
+            .expect("Something went wrong inside the framebuffer lock")
+            .clone();
+        write_checkpoint(&self.path, &self.header, &framebuffer)?;
+        self.last_written = Instant::now();
+        Ok(())
+    }
+}
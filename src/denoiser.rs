@@ -0,0 +1,140 @@
+use std::thread;
+
+use crate::framebuffer::{Features, FrameBuffer};
+use crate::prelude::*;
+
+use num_cpus::get_physical;
+
+const KERNEL: [f64; 3] = [3. / 8., 1. / 4., 1. / 16.];
+
+/// Edge-avoiding À-Trous wavelet filter (Dammertz et al. 2010), guided by the
+/// albedo, normal and depth buffers gathered alongside the colour.
+pub struct Denoiser {
+    iterations: u32,
+    sigma_colour: f64,
+    sigma_normal: f64,
+    sigma_albedo: f64,
+    sigma_depth: f64,
+}
+
+impl Default for Denoiser {
+    fn default() -> Self {
+        Self {
+            iterations: 5,
+            sigma_colour: 0.5,
+            sigma_normal: 0.1,
+            sigma_albedo: 0.1,
+            sigma_depth: 0.1,
+        }
+    }
+}
+
+struct Guide {
+    width: usize,
+    height: usize,
+    samples: Vec<u32>,
+    features: Vec<Features>,
+}
+
+impl Denoiser {
+    pub fn denoise(&self, framebuffer: &FrameBuffer) -> Vec<Colour> {
+        let guide = Guide {
+            width: framebuffer.width() as usize,
+            height: framebuffer.height() as usize,
+            samples: framebuffer.sample_counts(),
+            features: framebuffer.features(),
+        };
+
+        let mut input = framebuffer.pixels();
+        let mut output = vec![Colour::default(); input.len()];
+        let mut sigma_colour = self.sigma_colour;
+        for iteration in 0..self.iterations {
+            self.filter(&guide, &input, &mut output, 1 << iteration, sigma_colour);
+            std::mem::swap(&mut input, &mut output);
+            sigma_colour /= 2.;
+        }
+        input
+    }
+
+    fn filter(
+        &self,
+        guide: &Guide,
+        input: &[Colour],
+        output: &mut [Colour],
+        step: usize,
+        sigma_colour: f64,
+    ) {
+        let rows_per_thread = (guide.height / get_physical()).max(1);
+        thread::scope(|scope| {
+            for (chunk_id, chunk) in output.chunks_mut(rows_per_thread * guide.width).enumerate() {
+                scope.spawn(move || {
+                    let first_row = chunk_id * rows_per_thread;
+                    for (offset, pixel) in chunk.iter_mut().enumerate() {
+                        let x = offset % guide.width;
+                        let y = first_row + offset / guide.width;
+                        *pixel = self.filter_pixel(guide, input, x, y, step, sigma_colour);
+                    }
+                });
+            }
+        });
+    }
+
+    fn filter_pixel(
+        &self,
+        guide: &Guide,
+        input: &[Colour],
+        x: usize,
+        y: usize,
+        step: usize,
+        sigma_colour: f64,
+    ) -> Colour {
+        let p = y * guide.width + x;
+        if guide.samples[p] == 0 {
+            return input[p];
+        }
+
+        let mut sum = Colour::default();
+        let mut total_weight = 0.;
+        for dy in -2i64..=2 {
+            let qy = y as i64 + dy * step as i64;
+            if qy < 0 || qy >= guide.height as i64 {
+                continue;
+            }
+            for dx in -2i64..=2 {
+                let qx = x as i64 + dx * step as i64;
+                if qx < 0 || qx >= guide.width as i64 {
+                    continue;
+                }
+                let q = qy as usize * guide.width + qx as usize;
+                if guide.samples[q] == 0 {
+                    continue;
+                }
+
+                let (fp, fq) = (&guide.features[p], &guide.features[q]);
+                let colour_distance = (input[p] - input[q]).length_squared();
+                let normal_distance =
+                    (fp.normal - fq.normal).length_squared() / (step * step) as f64;
+                let albedo_distance = (fp.albedo - fq.albedo).length_squared();
+                let depth_scale = fp.depth.max(fq.depth);
+                let depth_distance = match depth_scale > 0. {
+                    true => (fp.depth - fq.depth).abs() / depth_scale,
+                    false => 0.,
+                };
+
+                let weight = KERNEL[dx.unsigned_abs() as usize]
+                    * KERNEL[dy.unsigned_abs() as usize]
+                    * (-colour_distance / sigma_colour).exp()
+                    * (-normal_distance / self.sigma_normal).exp()
+                    * (-albedo_distance / self.sigma_albedo).exp()
+                    * (-depth_distance / self.sigma_depth).exp();
+                sum += weight * input[q];
+                total_weight += weight;
+            }
+        }
+
+        match total_weight > 0. {
+            true => sum / total_weight,
+            false => input[p],
+        }
+    }
+}
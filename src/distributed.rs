@@ -0,0 +1,482 @@
+use std::collections::VecDeque;
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Read, Result, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::camera::CameraSettings;
+use crate::framebuffer::FrameBuffer;
+use crate::integrator::{find_integrator, IntegratorKind};
+use crate::job::PixelTracer;
+use crate::scene::find_scene;
+use crate::settings::RenderSettings;
+use crate::wire::*;
+
+const MAGIC: &[u8; 8] = b"RTIOWNET";
+const VERSION: u32 = 10;
+
+const MESSAGE_TILE: u8 = 1;
+const MESSAGE_DONE: u8 = 2;
+
+/// Everything a worker needs to reproduce the coordinator's view of the scene.
+pub struct JobDescription {
+    pub scene: String,
+    pub aspect_ratio: f64,
+    pub settings: RenderSettings,
+    pub camera: CameraSettings,
+    /// How long either end waits to hear from the other before giving up on
+    /// it, which must allow for rendering a whole tile.
+    pub tile_timeout: Duration,
+}
+
+impl JobDescription {
+    fn write_to(&self, out: &mut impl Write) -> Result<()> {
+        write_string(out, &self.scene)?;
+        write_f64(out, self.aspect_ratio)?;
+        write_u32(out, self.settings.width)?;
+        write_u32(out, self.settings.height)?;
+        write_u32(out, self.settings.samples_per_pixel)?;
+        write_u32(out, self.settings.max_depth)?;
+        write_u32(out, self.settings.diffuse_depth)?;
+        write_u32(out, self.settings.specular_depth)?;
+        write_u32(out, self.settings.transmission_depth)?;
+        write_u32(out, self.settings.roulette_depth)?;
+        write_u64(out, self.settings.seed)?;
+        write_string(out, self.settings.integrator.name())?;
+        write_u8(out, self.settings.spectral as u8)?;
+        write_vector(out, &self.camera.look_from)?;
+        write_vector(out, &self.camera.look_at)?;
+        write_vector(out, &self.camera.up_vector)?;
+        write_f64(out, self.camera.v_fov)?;
+        write_f64(out, self.camera.aperture)?;
+        write_f64(out, self.camera.focus_dist)?;
+        write_f64(out, self.tile_timeout.as_secs_f64())
+    }
+
+    fn read_from(input: &mut impl Read) -> Result<JobDescription> {
+        Ok(JobDescription {
+            scene: read_string(input)?,
+            aspect_ratio: read_f64(input)?,
+            settings: RenderSettings {
+                width: read_u32(input)?,
+                height: read_u32(input)?,
+                samples_per_pixel: read_u32(input)?,
+                max_depth: read_u32(input)?,
+                diffuse_depth: read_u32(input)?,
+                specular_depth: read_u32(input)?,
+                transmission_depth: read_u32(input)?,
+                roulette_depth: read_u32(input)?,
+                seed: read_u64(input)?,
+                integrator: {
+                    let name = read_string(input)?;
+                    let integrator = find_integrator(&name).ok_or_else(|| {
+                        Error::new(
+                            ErrorKind::InvalidData,
+                            format!("Unknown integrator '{}'", name),
+                        )
+                    })?;
+                    check_integrator(integrator)?;
+                    integrator
+                },
+                spectral: read_u8(input)? != 0,
+                ..Default::default()
+            },
+            camera: CameraSettings {
+                look_from: read_vector(input)?,
+                look_at: read_vector(input)?,
+                up_vector: read_vector(input)?,
+                v_fov: read_f64(input)?,
+                aperture: read_f64(input)?,
+                focus_dist: read_f64(input)?,
+            },
+            tile_timeout: {
+                let seconds = read_f64(input)?;
+                Duration::try_from_secs_f64(seconds)
+                    .map_err(|error| Error::new(ErrorKind::InvalidData, error))?
+            },
+        })
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Tile {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+impl Tile {
+    fn write_to(&self, out: &mut impl Write) -> Result<()> {
+        write_u32(out, self.x)?;
+        write_u32(out, self.y)?;
+        write_u32(out, self.width)?;
+        write_u32(out, self.height)
+    }
+
+    fn read_from(input: &mut impl Read) -> Result<Tile> {
+        Ok(Tile {
+            x: read_u32(input)?,
+            y: read_u32(input)?,
+            width: read_u32(input)?,
+            height: read_u32(input)?,
+        })
+    }
+}
+
+struct TileQueue {
+    pending: VecDeque<Tile>,
+    completed: usize,
+    total: usize,
+}
+
+struct Coordinator {
+    queue: Mutex<TileQueue>,
+    changed: Condvar,
+    framebuffer_ref: Arc<Mutex<FrameBuffer>>,
+}
+
+impl Coordinator {
+    /// Blocks until there is a tile to hand out, or returns `None` once every
+    /// tile has come back.
+    fn next_tile(&self) -> Option<Tile> {
+        let mut queue = self
+            .queue
+            .lock()
+            .expect("Something went wrong inside the tile queue lock");
+        loop {
+            if let Some(tile) = queue.pending.pop_front() {
+                return Some(tile);
+            }
+            if queue.completed == queue.total {
+                return None;
+            }
+            queue = self
+                .changed
+                .wait(queue)
+                .expect("Something went wrong inside the tile queue lock");
+        }
+    }
+
+    fn complete(&self, tile: &Tile, result: &FrameBuffer) {
+        self.framebuffer_ref
+            .lock()
+            .expect("Something went wrong inside the framebuffer lock")
+            .merge_tile(result, tile.x, tile.y);
+        let mut queue = self
+            .queue
+            .lock()
+            .expect("Something went wrong inside the tile queue lock");
+        queue.completed += 1;
+        println!("Received tile {} of {}", queue.completed, queue.total);
+        self.changed.notify_all();
+    }
+
+    fn reassign(&self, tile: Tile) {
+        self.queue
+            .lock()
+            .expect("Something went wrong inside the tile queue lock")
+            .pending
+            .push_front(tile);
+        self.changed.notify_all();
+    }
+
+    fn is_done(&self) -> bool {
+        let queue = self
+            .queue
+            .lock()
+            .expect("Something went wrong inside the tile queue lock");
+        queue.completed == queue.total
+    }
+
+    /// Feeds tiles to one worker until the image is complete. A tile that was
+    /// in flight when the worker disconnected, or took longer than the tile
+    /// timeout, goes back on the queue.
+    fn serve(&self, stream: TcpStream, description: &JobDescription) -> Result<()> {
+        stream.set_read_timeout(Some(description.tile_timeout))?;
+        stream.set_write_timeout(Some(description.tile_timeout))?;
+        let mut input = BufReader::new(stream.try_clone()?);
+        let mut out = BufWriter::new(stream);
+
+        let mut magic = [0u8; 8];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC || read_u32(&mut input)? != VERSION {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "Worker is not running a compatible version of rtiow",
+            ));
+        }
+        description.write_to(&mut out)?;
+        out.flush()?;
+
+        while let Some(tile) = self.next_tile() {
+            let result = write_u8(&mut out, MESSAGE_TILE)
+                .and_then(|_| tile.write_to(&mut out))
+                .and_then(|_| out.flush())
+                .and_then(|_| FrameBuffer::read_from(&mut input, tile.width, tile.height));
+            match result {
+                Ok(tile_result) => self.complete(&tile, &tile_result),
+                Err(error) => {
+                    self.reassign(tile);
+                    return Err(error);
+                }
+            }
+        }
+
+        write_u8(&mut out, MESSAGE_DONE)?;
+        out.flush()
+    }
+}
+
+/// Tiles only carry the samples traced for their own pixels, so integrators
+/// that send light to other pixels can't be distributed.
+fn check_integrator(integrator: IntegratorKind) -> Result<()> {
+    match integrator {
+        IntegratorKind::Bidirectional => Err(Error::new(
+            ErrorKind::InvalidInput,
+            "The bdpt integrator is not supported by distributed renders",
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Listens on `address` and renders the image by handing tiles out to the
+/// workers that connect, until every tile has been returned. Fails for the
+/// bidirectional integrator, whose light paths land outside their tile.
+pub fn render_distributed(
+    address: &str,
+    framebuffer_ref: Arc<Mutex<FrameBuffer>>,
+    description: JobDescription,
+    tile_size: u32,
+) -> Result<()> {
+    check_integrator(description.settings.integrator)?;
+    let listener = TcpListener::bind(address)?;
+    coordinate(listener, framebuffer_ref, description, tile_size)
+}
+
+fn coordinate(
+    listener: TcpListener,
+    framebuffer_ref: Arc<Mutex<FrameBuffer>>,
+    description: JobDescription,
+    tile_size: u32,
+) -> Result<()> {
+    let mut pending = VecDeque::new();
+    for y in (0..description.settings.height).step_by(tile_size as usize) {
+        for x in (0..description.settings.width).step_by(tile_size as usize) {
+            pending.push_back(Tile {
+                x,
+                y,
+                width: tile_size.min(description.settings.width - x),
+                height: tile_size.min(description.settings.height - y),
+            });
+        }
+    }
+    let total = pending.len();
+
+    let coordinator = Arc::new(Coordinator {
+        queue: Mutex::new(TileQueue {
+            pending,
+            completed: 0,
+            total,
+        }),
+        changed: Condvar::new(),
+        framebuffer_ref,
+    });
+    let description = Arc::new(description);
+
+    listener.set_nonblocking(true)?;
+    println!("Waiting for workers on {}", listener.local_addr()?);
+
+    let started = Instant::now();
+    while !coordinator.is_done() {
+        match listener.accept() {
+            Ok((stream, peer)) => {
+                stream.set_nonblocking(false)?;
+                println!("Worker connected from {}", peer);
+                let coordinator_local = coordinator.clone();
+                let description_local = description.clone();
+                thread::spawn(move || {
+                    if let Err(error) = coordinator_local.serve(stream, &description_local) {
+                        println!("Worker {} disconnected: {}", peer, error);
+                    }
+                });
+            }
+            Err(error) if error.kind() == ErrorKind::WouldBlock => {
+                thread::sleep(Duration::from_millis(50));
+            }
+            Err(error) => return Err(error),
+        }
+    }
+    println!(
+        "Rendered {} tiles in {:.1}s",
+        total,
+        started.elapsed().as_secs_f64()
+    );
+    Ok(())
+}
+
+/// Connects to a coordinator and renders the tiles it hands out until told
+/// the image is complete.
+pub fn run_worker(address: impl ToSocketAddrs) -> Result<()> {
+    let stream = TcpStream::connect(address)?;
+    let mut input = BufReader::new(stream.try_clone()?);
+    let mut out = BufWriter::new(stream);
+
+    out.write_all(MAGIC)?;
+    write_u32(&mut out, VERSION)?;
+    out.flush()?;
+
+    let description = JobDescription::read_from(&mut input)?;
+    // Between tiles the coordinator may be waiting on another worker's tile
+    // for up to the timeout before it has anything to send.
+    input
+        .get_ref()
+        .set_read_timeout(Some(2 * description.tile_timeout))?;
+    let build_scene = find_scene(&description.scene).ok_or_else(|| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("Unknown scene '{}'", description.scene),
+        )
+    })?;
+    let scene = build_scene();
+    let camera = description.camera.build(description.aspect_ratio);
+    let integrator = description.settings.integrator.build();
+    let tracer = PixelTracer {
+        scene: &scene,
+        camera: &camera,
+        integrator: integrator.as_ref(),
+        width: description.settings.width,
+        height: description.settings.height,
+        depths: description.settings.path_depths(),
+        seed: description.settings.seed,
+        spectral: description.settings.spectral,
+    };
+    let samples_per_pixel = description.settings.samples_per_pixel;
+
+    loop {
+        match read_u8(&mut input)? {
+            MESSAGE_TILE => {
+                let tile = Tile::read_from(&mut input)?;
+                if tile.x.saturating_add(tile.width) > description.settings.width
+                    || tile.y.saturating_add(tile.height) > description.settings.height
+                {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        "Tile lies outside the image",
+                    ));
+                }
+                let result = Mutex::new(FrameBuffer::new(tile.width, tile.height));
+                let rows = Mutex::new(0..tile.height);
+                thread::scope(|scope| {
+                    for _ in 0..description.settings.threads {
+                        scope.spawn(|| loop {
+                            let row = rows
+                                .lock()
+                                .expect("Something went wrong inside the tile lock")
+                                .next();
+                            let Some(row) = row else { break };
+                            for column in 0..tile.width {
+                                // Only bdpt splats, and reading the job rejects it.
+                                let (pixel_samples, _) = tracer.trace(
+                                    tile.x + column,
+                                    tile.y + row,
+                                    0,
+                                    samples_per_pixel,
+                                );
+                                result
+                                    .lock()
+                                    .expect("Something went wrong inside the tile lock")
+                                    .set_samples(column, row, &pixel_samples);
+                            }
+                        });
+                    }
+                });
+                result
+                    .into_inner()
+                    .expect("Something went wrong inside the tile lock")
+                    .write_to(&mut out)?;
+                out.flush()?;
+            }
+            MESSAGE_DONE => return Ok(()),
+            message => {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Unexpected message {} from coordinator", message),
+                ))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::integrator::IntegratorKind;
+
+    #[test]
+    fn workers_on_localhost_render_the_whole_image() {
+        let (width, height, samples) = (20, 12, 2);
+        let settings = RenderSettings {
+            width,
+            height,
+            samples_per_pixel: samples,
+            threads: 1,
+            // Normals seen through a pinhole don't depend on the random
+            // numbers each worker draws, so the image can be checked exactly.
+            integrator: IntegratorKind::Normals,
+            ..Default::default()
+        };
+        let camera = CameraSettings::default().aperture(0.);
+        let aspect_ratio = width as f64 / height as f64;
+        let description = JobDescription {
+            scene: "test".into(),
+            aspect_ratio,
+            settings,
+            camera,
+            tile_timeout: Duration::from_secs(30),
+        };
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let workers: Vec<_> = (0..2)
+            .map(|_| thread::spawn(move || run_worker(address)))
+            .collect();
+        let framebuffer_ref = Arc::new(Mutex::new(FrameBuffer::new(width, height)));
+        coordinate(listener, framebuffer_ref.clone(), description, 8).unwrap();
+        // A worker that connects after the last tile is in finds the
+        // coordinator gone, so only the image is checked.
+        for worker in workers {
+            let _ = worker.join().unwrap();
+        }
+
+        let scene = find_scene("test").unwrap()();
+        let camera = camera.build(aspect_ratio);
+        let integrator = settings.integrator.build();
+        let tracer = PixelTracer {
+            scene: &scene,
+            camera: &camera,
+            width,
+            height,
+            integrator: integrator.as_ref(),
+            depths: settings.path_depths(),
+            seed: settings.seed,
+            spectral: false,
+        };
+        let mut expected = FrameBuffer::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                expected.set_samples(x, y, &tracer.trace(x, y, 0, samples).0);
+            }
+        }
+
+        let framebuffer = framebuffer_ref.lock().unwrap();
+        assert_eq!(
+            framebuffer.sample_counts(),
+            vec![samples; (width * height) as usize]
+        );
+        for (pixel, expected) in framebuffer.pixels().iter().zip(expected.pixels()) {
+            assert!((pixel - expected).length() < 1e-9);
+        }
+    }
+}
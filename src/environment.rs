@@ -0,0 +1,403 @@
+use std::f64::consts::PI;
+use std::fmt::Debug;
+use std::io::Result;
+use std::path::Path;
+
+use crate::light::{cone_solid_angle, sample_cone};
+use crate::prelude::*;
+use crate::spectrum::xyz_to_rgb;
+use crate::texture::ImageTexture;
+
+use rand::{thread_rng, Rng};
+
+/// The light arriving from directions where rays leave the scene.
+pub trait Environment: Send + Sync + Debug {
+    fn radiance(&self, direction: &Vector3) -> Colour;
+    /// Picks a direction worth aiming shadow rays at, such as the sun, with
+    /// its pdf per unit solid angle. Environments without bright spots
+    /// return `None` and are only found by rays scattering into them.
+    fn sample(&self) -> Option<(Vector3, f64)> {
+        None
+    }
+    /// The pdf of `sample` picking `direction`.
+    fn pdf(&self, _direction: &Vector3) -> f64 {
+        0.
+    }
+}
+
+/// The blue to white gradient from the books.
+#[derive(Debug, Default)]
+pub struct Gradient;
+
+impl Environment for Gradient {
+    fn radiance(&self, direction: &Vector3) -> Colour {
+        let unit_direction = direction.unit_vector();
+        let t = 0.5 * (unit_direction.y() + 1.0);
+        (1. - t) * Colour::new(1, 1, 1) + t * Colour::new(0.5, 0.7, 1)
+    }
+}
+
+/// The same light from every direction, such as a dim night for scenes lit
+/// by their own objects.
+#[derive(Debug)]
+pub struct Uniform(Colour);
+
+impl Uniform {
+    pub fn new(colour: Colour) -> Self {
+        Self(colour)
+    }
+}
+
+impl Environment for Uniform {
+    fn radiance(&self, _direction: &Vector3) -> Colour {
+        self.0
+    }
+}
+
+/// Preetham's luminances are in kcd/m²; this brings a clear sky to around 1,
+/// like the gradient.
+const SKY_EXPOSURE: f64 = 0.05;
+/// The luminance of the sun's disc above the atmosphere, in kcd/m².
+const SUN_LUMINANCE: f64 = 2e6;
+const SUN_ANGULAR_DIAMETER: f64 = 0.53;
+/// Wavelengths in µm used for the red, green and blue sunlight.
+const SUN_WAVELENGTHS: [f64; 3] = [0.65, 0.55, 0.45];
+
+/// The coefficients of Perez's sky luminance distribution.
+#[derive(Debug)]
+struct Perez([f64; 5]);
+
+impl Perez {
+    fn new(turbidity: f64, coefficients: [[f64; 2]; 5]) -> Self {
+        Self(coefficients.map(|[slope, intercept]| slope * turbidity + intercept))
+    }
+
+    fn evaluate(&self, cos_theta: f64, gamma: f64) -> f64 {
+        let [a, b, c, d, e] = self.0;
+        (1. + a * (b / cos_theta).exp()) * (1. + c * (d * gamma).exp() + e * gamma.cos().powi(2))
+    }
+}
+
+/// A clear daylight sky using the analytic model of Preetham, Shirley and
+/// Smits (1999), with the sun as a small bright disc that shadow rays aim at.
+#[derive(Debug)]
+pub struct Sky {
+    sun_direction: Vector3,
+    sun_radiance: Colour,
+    cos_sun_radius: f64,
+    /// Zenith luminance and chromaticity, and the distributions around it.
+    zenith: [f64; 3],
+    perez: [Perez; 3],
+    /// Each distribution evaluated at the zenith, to normalise by.
+    perez_zenith: [f64; 3],
+}
+
+impl Sky {
+    /// `elevation` is the sun's angle above the horizon in degrees and
+    /// `azimuth` its angle around the vertical, with 0 along +x and 90 along
+    /// +z. `turbidity` runs from 2 for a very clear sky to about 10 for haze.
+    pub fn new(elevation: f64, azimuth: f64, turbidity: f64) -> Self {
+        let elevation = elevation.clamp(0.5, 90.).to_radians();
+        let azimuth = azimuth.to_radians();
+        let t = turbidity.clamp(1.7, 10.);
+        let sun_direction = Vector3::new(
+            elevation.cos() * azimuth.cos(),
+            elevation.sin(),
+            elevation.cos() * azimuth.sin(),
+        );
+        let theta_s = PI / 2. - elevation;
+
+        let chi = (4. / 9. - t / 120.) * (PI - 2. * theta_s);
+        let zenith_luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let chromaticity = |rows: [[f64; 4]; 3]| {
+            let powers = [theta_s.powi(3), theta_s.powi(2), theta_s, 1.];
+            let row = |coefficients: [f64; 4]| -> f64 {
+                coefficients.iter().zip(powers).map(|(c, p)| c * p).sum()
+            };
+            t * t * row(rows[0]) + t * row(rows[1]) + row(rows[2])
+        };
+        let zenith_x = chromaticity([
+            [0.00166, -0.00375, 0.00209, 0.],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        ]);
+        let zenith_y = chromaticity([
+            [0.00275, -0.00610, 0.00317, 0.],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        ]);
+
+        let perez = [
+            Perez::new(
+                t,
+                [
+                    [0.1787, -1.4630],
+                    [-0.3554, 0.4275],
+                    [-0.0227, 5.3251],
+                    [0.1206, -2.5771],
+                    [-0.0670, 0.3703],
+                ],
+            ),
+            Perez::new(
+                t,
+                [
+                    [-0.0193, -0.2592],
+                    [-0.0665, 0.0008],
+                    [-0.0004, 0.2125],
+                    [-0.0641, -0.8989],
+                    [-0.0033, 0.0452],
+                ],
+            ),
+            Perez::new(
+                t,
+                [
+                    [-0.0167, -0.2608],
+                    [-0.0950, 0.0092],
+                    [-0.0079, 0.2102],
+                    [-0.0441, -1.6537],
+                    [-0.0109, 0.0529],
+                ],
+            ),
+        ];
+        let perez_zenith = [0, 1, 2].map(|index| perez[index].evaluate(1., theta_s));
+
+        Self {
+            sun_direction,
+            sun_radiance: Self::sun_radiance(theta_s, t),
+            cos_sun_radius: (0.5 * SUN_ANGULAR_DIAMETER).to_radians().cos(),
+            zenith: [zenith_luminance, zenith_x, zenith_y],
+            perez,
+            perez_zenith,
+        }
+    }
+
+    /// The sun's disc dimmed and reddened by Rayleigh and aerosol scattering
+    /// along its path through the atmosphere.
+    fn sun_radiance(theta_s: f64, turbidity: f64) -> Colour {
+        let air_mass = 1. / (theta_s.cos() + 0.15 * (93.885 - theta_s.to_degrees()).powf(-1.253));
+        let beta = 0.04608 * turbidity - 0.04586;
+        let transmittance = SUN_WAVELENGTHS.map(|lambda| {
+            let rayleigh = (-0.008735 * lambda.powf(-4.08) * air_mass).exp();
+            let aerosol = (-beta * lambda.powf(-1.3) * air_mass).exp();
+            rayleigh * aerosol
+        });
+        SKY_EXPOSURE
+            * SUN_LUMINANCE
+            * Colour::new(transmittance[0], transmittance[1], transmittance[2])
+    }
+
+    pub fn sun_direction(&self) -> Vector3 {
+        self.sun_direction
+    }
+}
+
+impl Environment for Sky {
+    fn radiance(&self, direction: &Vector3) -> Colour {
+        let direction = direction.unit_vector();
+        let cos_gamma = direction.dot(&self.sun_direction).clamp(-1., 1.);
+        // Below the horizon, carry on with the sky just above it.
+        let cos_theta = direction.y().max(0.01);
+        let gamma = cos_gamma.acos();
+        let [luminance, x, y] = [0, 1, 2].map(|index| {
+            self.zenith[index] * self.perez[index].evaluate(cos_theta, gamma)
+                / self.perez_zenith[index]
+        });
+
+        let xyz = Vector3::new(x / y * luminance, luminance, (1. - x - y) / y * luminance);
+        let rgb = SKY_EXPOSURE * xyz_to_rgb(&xyz);
+        let sky = Colour::new(rgb.x().max(0.), rgb.y().max(0.), rgb.z().max(0.));
+        match cos_gamma >= self.cos_sun_radius {
+            true => sky + self.sun_radiance,
+            false => sky,
+        }
+    }
+
+    fn sample(&self) -> Option<(Vector3, f64)> {
+        Some((
+            sample_cone(&self.sun_direction, self.cos_sun_radius),
+            1. / cone_solid_angle(self.cos_sun_radius),
+        ))
+    }
+
+    fn pdf(&self, direction: &Vector3) -> f64 {
+        match direction.unit_vector().dot(&self.sun_direction) >= self.cos_sun_radius {
+            true => 1. / cone_solid_angle(self.cos_sun_radius),
+            false => 0.,
+        }
+    }
+}
+
+/// A piecewise constant distribution over [0, 1), split into equal cells.
+#[derive(Debug)]
+struct Distribution {
+    values: Vec<f64>,
+    cdf: Vec<f64>,
+    /// The sum of the values before any fallback, for building marginals.
+    integral: f64,
+}
+
+impl Distribution {
+    fn new(values: Vec<f64>) -> Self {
+        let values: Vec<f64> = values.into_iter().map(|value| value.max(0.)).collect();
+        let integral: f64 = values.iter().sum();
+        // With nothing to favour, fall back to sampling uniformly.
+        let values = match integral > 0. {
+            true => values,
+            false => vec![1.; values.len()],
+        };
+        let total: f64 = values.iter().sum();
+        let mut cdf = Vec::with_capacity(values.len() + 1);
+        let mut sum = 0.;
+        cdf.push(0.);
+        for value in values.iter() {
+            sum += value;
+            cdf.push(sum / total);
+        }
+        Self {
+            values: values.iter().map(|value| value / total).collect(),
+            cdf,
+            integral,
+        }
+    }
+
+    /// Picks a cell, returning it with the position within it.
+    fn sample(&self, u: f64) -> (usize, f64) {
+        let index = self
+            .cdf
+            .partition_point(|&sum| sum <= u)
+            .clamp(1, self.values.len())
+            - 1;
+        let width = self.cdf[index + 1] - self.cdf[index];
+        let offset = match width > 0. {
+            true => ((u - self.cdf[index]) / width).clamp(0., 1.),
+            false => 0.5,
+        };
+        (index, offset)
+    }
+
+    /// The density of the cell over [0, 1).
+    fn pdf(&self, index: usize) -> f64 {
+        self.values[index] * self.values.len() as f64
+    }
+}
+
+/// An HDR photograph of the surroundings in the equirectangular layout, with
+/// longitude across and latitude down the image. Shadow rays are aimed in
+/// proportion to its brightness, so small bright lights such as the sun are
+/// found without a flood of fireflies.
+#[derive(Debug)]
+pub struct EnvironmentMap {
+    image: ImageTexture,
+    rows: Distribution,
+    columns: Vec<Distribution>,
+    rotation: f64,
+    intensity: f64,
+}
+
+impl EnvironmentMap {
+    pub fn new(image: ImageTexture) -> Self {
+        let (width, height) = (image.width(), image.height());
+        // Rows near the poles cover less of the sphere, so they count for less.
+        let columns: Vec<Distribution> = (0..height)
+            .map(|y| {
+                let sin_theta = (PI * (y as f64 + 0.5) / height as f64).sin();
+                Distribution::new(
+                    (0..width)
+                        .map(|x| image.pixel(x, y).luminance().max(0.) * sin_theta)
+                        .collect(),
+                )
+            })
+            .collect();
+        let rows = Distribution::new(columns.iter().map(|row| row.integral).collect());
+        Self {
+            image,
+            rows,
+            columns,
+            rotation: 0.,
+            intensity: 1.,
+        }
+    }
+
+    /// Loads a Radiance .hdr image, or an 8-bit PPM image.
+    pub fn open(path: &Path) -> Result<Self> {
+        let image = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("hdr") => ImageTexture::open_hdr(path)?,
+            _ => ImageTexture::open(path)?,
+        };
+        Ok(Self::new(image))
+    }
+
+    /// Turns the surroundings about the vertical axis, in degrees.
+    pub fn with_rotation(mut self, rotation: f64) -> Self {
+        self.rotation = rotation.to_radians();
+        self
+    }
+
+    /// Scales the brightness of the whole map.
+    pub fn with_intensity(mut self, intensity: f64) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    /// Where a direction lands on the image, with v running down from the
+    /// top.
+    fn uv(&self, direction: &Vector3) -> (f64, f64) {
+        let direction = direction.unit_vector();
+        let phi = direction.z().atan2(direction.x()) - self.rotation;
+        let theta = direction.y().clamp(-1., 1.).acos();
+        ((phi / (2. * PI)).rem_euclid(1.), theta / PI)
+    }
+
+    fn direction(&self, u: f64, v: f64) -> Vector3 {
+        let phi = 2. * PI * u + self.rotation;
+        let theta = PI * v;
+        Vector3::new(
+            theta.sin() * phi.cos(),
+            theta.cos(),
+            theta.sin() * phi.sin(),
+        )
+    }
+
+    fn pixel(&self, u: f64, v: f64) -> (usize, usize) {
+        let (width, height) = (self.image.width(), self.image.height());
+        (
+            ((u * width as f64) as usize).min(width - 1),
+            ((v * height as f64) as usize).min(height - 1),
+        )
+    }
+
+    /// Converts a density over the image to one over solid angle.
+    fn solid_angle_pdf(&self, x: usize, y: usize, v: f64) -> f64 {
+        let sin_theta = (PI * v).sin();
+        match sin_theta > 0. {
+            true => self.rows.pdf(y) * self.columns[y].pdf(x) / (2. * PI * PI * sin_theta),
+            false => 0.,
+        }
+    }
+}
+
+impl Environment for EnvironmentMap {
+    fn radiance(&self, direction: &Vector3) -> Colour {
+        let (u, v) = self.uv(direction);
+        let (x, y) = self.pixel(u, v);
+        self.intensity * self.image.pixel(x, y)
+    }
+
+    fn sample(&self) -> Option<(Vector3, f64)> {
+        let mut rng = thread_rng();
+        let (y, row_offset) = self.rows.sample(rng.gen());
+        let (x, column_offset) = self.columns[y].sample(rng.gen());
+        let u = (x as f64 + column_offset) / self.image.width() as f64;
+        let v = (y as f64 + row_offset) / self.image.height() as f64;
+        match self.solid_angle_pdf(x, y, v) {
+            pdf if pdf > 0. => Some((self.direction(u, v), pdf)),
+            _ => None,
+        }
+    }
+
+    fn pdf(&self, direction: &Vector3) -> f64 {
+        let (u, v) = self.uv(direction);
+        let (x, y) = self.pixel(u, v);
+        self.solid_angle_pdf(x, y, v)
+    }
+}
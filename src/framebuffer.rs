@@ -0,0 +1,277 @@
+use std::fs::File;
+use std::io::{BufWriter, Read, Result, Write};
+use std::path::Path;
+
+use crate::prelude::*;
+use crate::scene::Scene;
+use crate::wire::*;
+
+#[derive(Clone, Copy, Default)]
+pub struct Features {
+    pub albedo: Colour,
+    pub normal: Vector3,
+    pub depth: f64,
+}
+
+impl Features {
+    /// The features of the first surface a camera ray hit.
+    pub fn from_hit(rec: &Hit) -> Features {
+        Features {
+            albedo: rec
+                .material
+                .as_ref()
+                .map(|mat| mat.albedo(rec))
+                .unwrap_or_default(),
+            normal: rec.normal,
+            depth: rec.t,
+        }
+    }
+
+    /// The features of a camera ray that left the scene without hitting
+    /// anything.
+    pub fn from_miss(scene: &Scene, ray: &Ray) -> Features {
+        Features {
+            albedo: scene.environment().radiance(&ray.direction()),
+            ..Default::default()
+        }
+    }
+
+    pub fn accumulate(&mut self, other: &Features) {
+        self.albedo += other.albedo;
+        self.normal += other.normal;
+        self.depth += other.depth;
+    }
+
+    pub fn average(&self, samples: u32) -> Features {
+        match samples {
+            0 => Features::default(),
+            _ => Features {
+                albedo: self.albedo / samples,
+                normal: self.normal / samples,
+                depth: self.depth / samples as f64,
+            },
+        }
+    }
+}
+
+/// The running sums of every sample traced for a pixel.
+#[derive(Clone, Copy, Default)]
+pub struct PixelSamples {
+    pub colour: Colour,
+    pub features: Features,
+    pub luminance_squared: f64,
+    pub count: u32,
+}
+
+impl PixelSamples {
+    pub fn add_sample(&mut self, colour: Colour, features: &Features) {
+        let luminance = colour.luminance();
+        self.colour += colour;
+        self.features.accumulate(features);
+        self.luminance_squared += luminance * luminance;
+        self.count += 1;
+    }
+
+    pub fn accumulate(&mut self, other: &PixelSamples) {
+        self.colour += other.colour;
+        self.features.accumulate(&other.features);
+        self.luminance_squared += other.luminance_squared;
+        self.count += other.count;
+    }
+
+    pub fn average(&self) -> Colour {
+        match self.count {
+            0 => Colour::default(),
+            _ => self.colour / self.count,
+        }
+    }
+
+    /// The standard error of the pixel's mean luminance relative to that mean,
+    /// or `None` until there are enough samples to estimate it.
+    pub fn relative_error(&self) -> Option<f64> {
+        if self.count < 2 {
+            return None;
+        }
+        let samples = self.count as f64;
+        let mean = self.colour.luminance() / samples;
+        let variance =
+            ((self.luminance_squared / samples - mean * mean) * samples / (samples - 1.)).max(0.);
+        Some((variance / samples).sqrt() / (mean + 0.01))
+    }
+}
+
+/// Accumulates the sum of every sample traced for each pixel, along with the
+/// feature buffers used to guide the denoiser.
+#[derive(Clone)]
+pub struct FrameBuffer {
+    width: u32,
+    height: u32,
+    samples: Vec<PixelSamples>,
+    /// Light that landed on each pixel from paths traced for other pixels,
+    /// shared out over every sample in the image.
+    splats: Vec<Colour>,
+    /// Samples traced from the camera since the splats were cleared, which
+    /// unlike the pixels' counts leaves out samples copied across a preview
+    /// block.
+    camera_samples: u64,
+}
+
+impl FrameBuffer {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            samples: vec![PixelSamples::default(); (width * height) as usize],
+            splats: vec![Colour::default(); (width * height) as usize],
+            camera_samples: 0,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn index(&self, x: u32, y: u32) -> usize {
+        (y * self.width + x) as usize
+    }
+
+    pub fn set_samples(&mut self, x: u32, y: u32, samples: &PixelSamples) {
+        let index = self.index(x, y);
+        self.samples[index] = *samples;
+    }
+
+    pub fn add_samples(&mut self, x: u32, y: u32, samples: &PixelSamples) {
+        let index = self.index(x, y);
+        self.samples[index].accumulate(samples);
+    }
+
+    pub fn add_splat(&mut self, x: u32, y: u32, colour: &Colour) {
+        let index = self.index(x, y);
+        self.splats[index] += colour;
+    }
+
+    /// Records samples traced from the camera, which the splats are shared out
+    /// over.
+    pub fn count_camera_samples(&mut self, samples: u32) {
+        self.camera_samples += samples as u64;
+    }
+
+    pub fn clear_splats(&mut self) {
+        self.splats.fill(Colour::default());
+        self.camera_samples = 0;
+    }
+
+    pub fn samples_at(&self, x: u32, y: u32) -> u32 {
+        self.samples[self.index(x, y)].count
+    }
+
+    pub fn sample_counts(&self) -> Vec<u32> {
+        self.samples.iter().map(|samples| samples.count).collect()
+    }
+
+    pub fn mean_samples_per_pixel(&self) -> f64 {
+        let total: u64 = self
+            .samples
+            .iter()
+            .map(|samples| samples.count as u64)
+            .sum();
+        total as f64 / self.samples.len().max(1) as f64
+    }
+
+    /// The average of every pixel's relative error, or `None` until every
+    /// pixel has enough samples to estimate it.
+    pub fn mean_relative_error(&self) -> Option<f64> {
+        let mut total = 0.;
+        for samples in self.samples.iter() {
+            total += samples.relative_error()?;
+        }
+        Some(total / self.samples.len().max(1) as f64)
+    }
+
+    /// The averaged colour of every pixel, bottom row first.
+    pub fn pixels(&self) -> Vec<Colour> {
+        // Splats are only ever added alongside camera samples, so when there
+        // are none the splats are zero too.
+        let samples_per_pixel =
+            (self.camera_samples as f64 / self.samples.len().max(1) as f64).max(f64::MIN_POSITIVE);
+        self.samples
+            .iter()
+            .zip(self.splats.iter())
+            .map(|(samples, splat)| samples.average() + splat / samples_per_pixel)
+            .collect()
+    }
+
+    /// The averaged features of every pixel, bottom row first.
+    pub fn features(&self) -> Vec<Features> {
+        self.samples
+            .iter()
+            .map(|samples| samples.features.average(samples.count))
+            .collect()
+    }
+
+    /// Adds the samples in `tile` to this framebuffer, with the tile's bottom
+    /// left corner placed at (`x`, `y`).
+    pub fn merge_tile(&mut self, tile: &FrameBuffer, x: u32, y: u32) {
+        for tile_y in 0..tile.height {
+            for tile_x in 0..tile.width {
+                self.add_samples(
+                    x + tile_x,
+                    y + tile_y,
+                    &tile.samples[tile.index(tile_x, tile_y)],
+                );
+            }
+        }
+    }
+
+    pub fn write_to(&self, out: &mut impl Write) -> Result<()> {
+        for samples in self.samples.iter() {
+            write_vector(out, &samples.colour)?;
+            write_vector(out, &samples.features.albedo)?;
+            write_vector(out, &samples.features.normal)?;
+            write_f64(out, samples.features.depth)?;
+            write_f64(out, samples.luminance_squared)?;
+            write_u32(out, samples.count)?;
+        }
+        for splat in self.splats.iter() {
+            write_vector(out, splat)?;
+        }
+        write_u64(out, self.camera_samples)
+    }
+
+    pub fn read_from(input: &mut impl Read, width: u32, height: u32) -> Result<FrameBuffer> {
+        let mut framebuffer = FrameBuffer::new(width, height);
+        for samples in framebuffer.samples.iter_mut() {
+            *samples = PixelSamples {
+                colour: read_vector(input)?,
+                features: Features {
+                    albedo: read_vector(input)?,
+                    normal: read_vector(input)?,
+                    depth: read_f64(input)?,
+                },
+                luminance_squared: read_f64(input)?,
+                count: read_u32(input)?,
+            };
+        }
+        for splat in framebuffer.splats.iter_mut() {
+            *splat = read_vector(input)?;
+        }
+        framebuffer.camera_samples = read_u64(input)?;
+        Ok(framebuffer)
+    }
+}
+
+pub fn write_ppm(path: &Path, width: u32, height: u32, pixels: &[Colour]) -> Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(out, "P3\n{} {}\n255", width, height)?;
+    for y in (0..height).rev() {
+        for x in 0..width {
+            let (r, g, b) = pixels[(y * width + x) as usize].to_rgb8();
+            writeln!(out, "{} {} {}", r, g, b)?;
+        }
+    }
+    out.flush()
+}
@@ -1,5 +1,8 @@
+use std::fmt::Debug;
 use std::sync::Arc;
 
+use crate::bvh::Aabb;
+use crate::light_tree::Emitter;
 use crate::prelude::*;
 
 #[derive(Clone)]
@@ -8,6 +11,12 @@ pub struct Hit {
     pub normal: Vector3,
     pub material: Option<Arc<dyn Material>>,
     pub t: f64,
+    /// Surface coordinates for texture lookups.
+    pub u: f64,
+    pub v: f64,
+    /// How the hit point moves with u and v, which gives the surface tangents.
+    pub dpdu: Vector3,
+    pub dpdv: Vector3,
     pub front_face: bool,
 }
 
@@ -18,6 +27,10 @@ impl Default for Hit {
             normal: Vector3::default(),
             material: None,
             t: 0.,
+            u: 0.,
+            v: 0.,
+            dpdu: Vector3::default(),
+            dpdv: Vector3::default(),
             front_face: true,
         }
     }
@@ -34,21 +47,35 @@ impl Hit {
     }
 }
 
-pub trait Hittable: Send + Sync {
+pub trait Hittable: Send + Sync + Debug {
     fn hit(&self, ray: &Ray, t_min: f64, t_max: f64, rec: &mut Hit) -> bool;
+    /// Objects that give off light describe themselves so that shadow rays
+    /// can be aimed at them.
+    fn emitter(&self) -> Option<Emitter> {
+        None
+    }
+    /// What the object is made of, for telling materials apart.
+    fn material(&self) -> Option<&Arc<dyn Material>> {
+        None
+    }
+    /// The box the object fits in, so the scene's BVH can pass it by. Objects
+    /// without one are tested by every ray.
+    fn bounding_box(&self) -> Option<Aabb> {
+        None
+    }
 }
 
 impl Hittable for &Vec<Box<dyn Hittable>> {
     fn hit(&self, ray: &Ray, t_min: f64, t_max: f64, rec: &mut Hit) -> bool {
-        let mut temp_rec: Hit = Default::default();
         let mut hit_anything = false;
         let mut closest_so_far = t_max;
 
+        // Objects only write to the record when they are hit, so each closer
+        // hit can simply overwrite the last.
         for object in self.iter() {
-            if object.hit(ray, t_min, closest_so_far, &mut temp_rec) {
+            if object.hit(ray, t_min, closest_so_far, rec) {
                 hit_anything = true;
-                closest_so_far = temp_rec.t;
-                *rec = temp_rec.clone();
+                closest_so_far = rec.t;
             }
         }
 
@@ -0,0 +1,58 @@
+use std::io::Result;
+use std::path::Path;
+
+use crate::framebuffer::write_ppm;
+use crate::prelude::*;
+
+/// A finished render. Like the framebuffer it came from, row 0 is the bottom
+/// of the picture.
+#[derive(Clone, Debug)]
+pub struct Image {
+    width: u32,
+    height: u32,
+    pixels: Vec<Colour>,
+}
+
+impl Image {
+    pub fn new(width: u32, height: u32, pixels: Vec<Colour>) -> Self {
+        assert_eq!(pixels.len(), (width * height) as usize);
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The linear colour of each pixel, bottom row first.
+    pub fn pixels(&self) -> &[Colour] {
+        &self.pixels
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> Colour {
+        self.pixels[(y * self.width + x) as usize]
+    }
+
+    /// Gamma-corrected RGB bytes, top row first, as most image formats expect.
+    pub fn to_rgb8(&self) -> Vec<u8> {
+        (0..self.height)
+            .rev()
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .flat_map(|(x, y)| {
+                let (r, g, b) = self.pixel(x, y).to_rgb8();
+                [r, g, b]
+            })
+            .collect()
+    }
+
+    pub fn write_ppm(&self, path: &Path) -> Result<()> {
+        write_ppm(path, self.width, self.height, &self.pixels)
+    }
+}
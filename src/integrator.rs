@@ -0,0 +1,680 @@
+use std::fmt::Debug;
+
+use crate::bdpt::Bidirectional;
+use crate::camera::Camera;
+use crate::framebuffer::Features;
+use crate::materials::microfacet::{sample_cosine_hemisphere, Frame};
+use crate::prelude::*;
+use crate::scene::Scene;
+use crate::spectrum::{Spectrum, Wavelengths};
+use crate::stats::{count, local_count, Counter};
+
+use rand::{thread_rng, Rng};
+
+/// Turns a camera ray into the colour seen along it.
+pub trait Integrator: Send + Sync + Debug {
+    /// The colour for one sample. Rays traced in spectral mode carry their
+    /// wavelengths; integrators that work in RGB can ignore them. Light that
+    /// lands on other pixels goes to `film`.
+    fn colour(&self, ray: &Ray, scene: &Scene, depths: &PathDepths, film: &mut Film) -> Colour;
+}
+
+/// Light that a sample sent to some other pixel.
+pub struct Splat {
+    pub x: u32,
+    pub y: u32,
+    pub colour: Colour,
+}
+
+/// What an integrator knows of the image: the camera looking at the scene,
+/// and where light from paths that reach it lands.
+pub struct Film<'a> {
+    camera: &'a Camera,
+    width: u32,
+    height: u32,
+    splats: Vec<Splat>,
+    features: Option<Features>,
+}
+
+impl<'a> Film<'a> {
+    pub fn new(camera: &'a Camera, width: u32, height: u32) -> Self {
+        Self {
+            camera,
+            width,
+            height,
+            splats: vec![],
+            features: None,
+        }
+    }
+
+    pub fn camera(&self) -> &Camera {
+        self.camera
+    }
+
+    /// Pixels are mapped onto the camera's unit square through one less than
+    /// the image size, so the image reaches a little beyond it.
+    fn coverage(&self) -> f64 {
+        let stretch = |size: u32| size as f64 / size.saturating_sub(1).max(1) as f64;
+        stretch(self.width) * stretch(self.height)
+    }
+
+    /// How strongly the image responds to light arriving back along
+    /// `direction`; see `Camera::importance`.
+    pub fn importance(&self, direction: &Vector3) -> f64 {
+        self.camera.importance(direction, self.coverage())
+    }
+
+    /// The pdf per unit solid angle of a camera ray leaving along `direction`.
+    pub fn pdf_direction(&self, direction: &Vector3) -> f64 {
+        self.camera.pdf_direction(direction, self.coverage())
+    }
+
+    /// The pixel that a ray from `lens_point` along `direction` passes through,
+    /// if any.
+    pub fn pixel(&self, lens_point: &Point3, direction: &Vector3) -> Option<(u32, u32)> {
+        let (s, t) = self.camera.raster_position(lens_point, direction)?;
+        let x = s * self.width.saturating_sub(1) as f64;
+        let y = t * self.height.saturating_sub(1) as f64;
+        match (0. ..self.width as f64).contains(&x) && (0. ..self.height as f64).contains(&y) {
+            true => Some((x as u32, y as u32)),
+            false => None,
+        }
+    }
+
+    pub fn splat(&mut self, x: u32, y: u32, colour: Colour) {
+        self.splats.push(Splat { x, y, colour });
+    }
+
+    /// Keeps the denoiser's features for the current sample from the surface
+    /// the camera ray hit, so the ray needn't be traced a second time.
+    pub fn record_hit(&mut self, rec: &Hit) {
+        self.features = Some(Features::from_hit(rec));
+    }
+
+    /// Like `record_hit`, for a camera ray that hit nothing.
+    pub fn record_miss(&mut self, scene: &Scene, ray: &Ray) {
+        self.features = Some(Features::from_miss(scene, ray));
+    }
+
+    /// Finds the first hit along a camera ray, recording its features.
+    pub fn trace_camera_ray(&mut self, scene: &Scene, ray: &Ray, rec: &mut Hit) -> bool {
+        let hit = scene.hit(ray, 0.0001, f64::INFINITY, rec);
+        match hit {
+            true => self.record_hit(rec),
+            false => self.record_miss(scene, ray),
+        }
+        hit
+    }
+
+    /// The features recorded for the current sample, if the integrator
+    /// recorded any.
+    pub fn take_features(&mut self) -> Option<Features> {
+        self.features.take()
+    }
+
+    pub fn into_splats(self) -> Vec<Splat> {
+        self.splats
+    }
+}
+
+/// The integrators that can be chosen in `RenderSettings`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IntegratorKind {
+    Path,
+    Direct,
+    AmbientOcclusion,
+    Normals,
+    Uvs,
+    Depth,
+    MaterialIds,
+    TraversalCost,
+    Bidirectional,
+}
+
+pub const INTEGRATORS: [(&str, IntegratorKind); 9] = [
+    ("path", IntegratorKind::Path),
+    ("direct", IntegratorKind::Direct),
+    ("ao", IntegratorKind::AmbientOcclusion),
+    ("normals", IntegratorKind::Normals),
+    ("uvs", IntegratorKind::Uvs),
+    ("depth", IntegratorKind::Depth),
+    ("materials", IntegratorKind::MaterialIds),
+    ("cost", IntegratorKind::TraversalCost),
+    ("bdpt", IntegratorKind::Bidirectional),
+];
+
+pub fn find_integrator(name: &str) -> Option<IntegratorKind> {
+    INTEGRATORS
+        .iter()
+        .find(|(integrator_name, _)| *integrator_name == name)
+        .map(|(_, kind)| *kind)
+}
+
+impl IntegratorKind {
+    pub fn name(&self) -> &'static str {
+        INTEGRATORS
+            .iter()
+            .find(|(_, kind)| kind == self)
+            .map(|(name, _)| *name)
+            .unwrap_or_default()
+    }
+
+    pub fn build(&self) -> Box<dyn Integrator> {
+        match self {
+            IntegratorKind::Path => Box::new(PathTracer),
+            IntegratorKind::Direct => Box::new(DirectLighting),
+            IntegratorKind::AmbientOcclusion => Box::new(AmbientOcclusion::new(1.)),
+            IntegratorKind::Normals => Box::new(Normals),
+            IntegratorKind::Uvs => Box::new(Uvs),
+            IntegratorKind::Depth => Box::new(Depth::new(20.)),
+            IntegratorKind::MaterialIds => Box::new(MaterialIds),
+            IntegratorKind::TraversalCost => Box::new(TraversalCost),
+            IntegratorKind::Bidirectional => Box::new(Bidirectional),
+        }
+    }
+}
+
+/// How many bounces a path may take: in total, of each kind, and before
+/// Russian roulette may end it early.
+#[derive(Clone, Copy, Debug)]
+pub struct PathDepths {
+    pub max: u32,
+    pub diffuse: u32,
+    pub specular: u32,
+    pub transmission: u32,
+    pub roulette: u32,
+}
+
+impl PathDepths {
+    fn limit(&self, kind: BounceKind) -> u32 {
+        match kind {
+            BounceKind::Diffuse => self.diffuse,
+            BounceKind::Specular => self.specular,
+            BounceKind::Transmission => self.transmission,
+        }
+    }
+}
+
+/// Follows each ray from bounce to bounce, gathering light from the lights at
+/// every hit, until it runs into one of the depth limits. After
+/// `depths.roulette` bounces, paths carrying little light are ended at random
+/// and the survivors weighted up to make up for them.
+#[derive(Debug)]
+pub struct PathTracer;
+
+impl Integrator for PathTracer {
+    fn colour(&self, ray: &Ray, scene: &Scene, depths: &PathDepths, film: &mut Film) -> Colour {
+        match ray.wavelengths() {
+            Some(_) => {
+                // Dispersion may have terminated some of the wavelengths on the way.
+                let (radiance, wavelengths) = self.spectral_colour(ray, scene, depths, film);
+                wavelengths.to_rgb(&radiance)
+            }
+            None => self.rgb_colour(ray, scene, depths, film),
+        }
+    }
+}
+
+impl PathTracer {
+    fn rgb_colour(&self, ray: &Ray, scene: &Scene, depths: &PathDepths, film: &mut Film) -> Colour {
+        let mut radiance = Colour::default();
+        let mut throughput = Colour::new(1, 1, 1);
+        let mut ray = ray.clone();
+        // The pdf `scatter` chose the current ray with, or zero for camera
+        // rays and smooth surfaces.
+        let mut scatter_pdf = 0.;
+        let mut bounces = [0; 3];
+
+        for bounce in 0..depths.max {
+            let mut rec: Hit = Default::default();
+            let hit = scene.hit(&ray, 0.0001, f64::INFINITY, &mut rec);
+            if bounce == 0 {
+                match hit {
+                    true => film.record_hit(&rec),
+                    false => film.record_miss(scene, &ray),
+                }
+            }
+            if !hit {
+                return radiance + throughput * environment_light(scene, &ray, scatter_pdf);
+            }
+            let Some(mat) = rec.material.as_ref() else {
+                return radiance;
+            };
+            radiance += throughput
+                * (emitted_light(scene, &ray, mat.as_ref(), &rec, scatter_pdf)
+                    + direct_light(scene, &ray, mat.as_ref(), &rec));
+            let ScatterResult::Scattered {
+                attenuation,
+                scattered,
+                kind,
+                delta,
+            } = mat.scatter(&ray, &rec)
+            else {
+                return radiance;
+            };
+            bounces[kind as usize] += 1;
+            if bounces[kind as usize] > depths.limit(kind) {
+                count(Counter::DepthTerminations);
+                return radiance;
+            }
+            count(Counter::BounceRays);
+            scatter_pdf = match delta {
+                true => 0.,
+                false => mat.pdf(&ray, &rec, &scattered.direction().unit_vector()),
+            };
+            throughput *= attenuation;
+            ray = scattered;
+
+            if bounce + 1 >= depths.roulette {
+                match survival_probability(throughput.max_component()) {
+                    Some(survival) => throughput /= survival,
+                    None => return radiance,
+                }
+            }
+        }
+
+        count(Counter::DepthTerminations);
+        radiance
+    }
+
+    /// Like `rgb_colour`, but carrying the radiance at the ray's wavelengths.
+    /// Returns them too, as they were left once dispersion had terminated any.
+    /// Materials work in RGB, so their attenuation is upsampled at each bounce.
+    fn spectral_colour(
+        &self,
+        ray: &Ray,
+        scene: &Scene,
+        depths: &PathDepths,
+        film: &mut Film,
+    ) -> (Spectrum, Wavelengths) {
+        let mut radiance = Spectrum::default();
+        let mut throughput = Spectrum::splat(1.);
+        let mut ray = ray.clone();
+        let mut scatter_pdf = 0.;
+        let mut bounces = [0; 3];
+
+        for bounce in 0..depths.max {
+            let wavelengths = *ray
+                .wavelengths()
+                .expect("Spectral rays must carry wavelengths");
+            let mut rec: Hit = Default::default();
+            let hit = scene.hit(&ray, 0.0001, f64::INFINITY, &mut rec);
+            if bounce == 0 {
+                match hit {
+                    true => film.record_hit(&rec),
+                    false => film.record_miss(scene, &ray),
+                }
+            }
+            if !hit {
+                let environment = environment_light(scene, &ray, scatter_pdf);
+                return (
+                    radiance + throughput * Spectrum::from_rgb(&environment, &wavelengths),
+                    wavelengths,
+                );
+            }
+            let Some(mat) = rec.material.as_ref() else {
+                return (radiance, wavelengths);
+            };
+            let emitted = emitted_light(scene, &ray, mat.as_ref(), &rec, scatter_pdf)
+                + direct_light(scene, &ray, mat.as_ref(), &rec);
+            radiance = radiance + throughput * Spectrum::from_rgb(&emitted, &wavelengths);
+            let ScatterResult::Scattered {
+                attenuation,
+                scattered,
+                kind,
+                delta,
+            } = mat.scatter(&ray, &rec)
+            else {
+                return (radiance, wavelengths);
+            };
+            bounces[kind as usize] += 1;
+            if bounces[kind as usize] > depths.limit(kind) {
+                count(Counter::DepthTerminations);
+                return (radiance, wavelengths);
+            }
+            count(Counter::BounceRays);
+            // Materials that split wavelengths set their own.
+            let scattered_wavelengths = scattered.wavelengths().copied().unwrap_or(wavelengths);
+            scatter_pdf = match delta {
+                true => 0.,
+                false => mat.pdf(&ray, &rec, &scattered.direction().unit_vector()),
+            };
+            throughput = throughput * Spectrum::from_rgb(&attenuation, &scattered_wavelengths);
+            ray = scattered.with_wavelengths(scattered_wavelengths);
+
+            if bounce + 1 >= depths.roulette {
+                match survival_probability(throughput.max_value()) {
+                    Some(survival) => throughput = throughput / survival,
+                    None => return (radiance, scattered_wavelengths),
+                }
+            }
+        }
+
+        count(Counter::DepthTerminations);
+        let wavelengths = *ray
+            .wavelengths()
+            .expect("Spectral rays must carry wavelengths");
+        (radiance, wavelengths)
+    }
+}
+
+/// Only the light arriving straight from the lights, emitters and environment
+/// at the first hit, with no indirect bounces. Shadow rays are combined with
+/// one scattered ray, which finds light the shadow rays can't aim for.
+#[derive(Debug)]
+pub struct DirectLighting;
+
+impl Integrator for DirectLighting {
+    fn colour(&self, ray: &Ray, scene: &Scene, _depths: &PathDepths, film: &mut Film) -> Colour {
+        let mut rec: Hit = Default::default();
+        if !film.trace_camera_ray(scene, ray, &mut rec) {
+            return environment_light(scene, ray, 0.);
+        }
+        let Some(mat) = rec.material.as_ref() else {
+            return Colour::default();
+        };
+        let direct = mat.emitted(&rec) + direct_light(scene, ray, mat.as_ref(), &rec);
+        let ScatterResult::Scattered {
+            attenuation,
+            scattered,
+            delta,
+            ..
+        } = mat.scatter(ray, &rec)
+        else {
+            return direct;
+        };
+
+        count(Counter::BounceRays);
+        let scatter_pdf = match delta {
+            true => 0.,
+            false => mat.pdf(ray, &rec, &scattered.direction().unit_vector()),
+        };
+        let mut light_rec: Hit = Default::default();
+        let found = match scene.hit(&scattered, 0.0001, f64::INFINITY, &mut light_rec) {
+            true => match light_rec.material.as_ref() {
+                Some(light_mat) => emitted_light(
+                    scene,
+                    &scattered,
+                    light_mat.as_ref(),
+                    &light_rec,
+                    scatter_pdf,
+                ),
+                None => Colour::default(),
+            },
+            false => environment_light(scene, &scattered, scatter_pdf),
+        };
+        direct + attenuation * found
+    }
+}
+
+/// White where the hemisphere above the first hit is open within `distance`,
+/// darkening where other surfaces close it off.
+#[derive(Debug)]
+pub struct AmbientOcclusion {
+    distance: f64,
+}
+
+impl AmbientOcclusion {
+    pub fn new(distance: f64) -> Self {
+        Self { distance }
+    }
+}
+
+impl Integrator for AmbientOcclusion {
+    fn colour(&self, ray: &Ray, scene: &Scene, _depths: &PathDepths, film: &mut Film) -> Colour {
+        let mut rec: Hit = Default::default();
+        if !film.trace_camera_ray(scene, ray, &mut rec) {
+            return Colour::new(1, 1, 1);
+        }
+        let mut rng = thread_rng();
+        let direction = Frame::from_normal(&rec.normal)
+            .to_world(&sample_cosine_hemisphere(rng.gen(), rng.gen()));
+        count(Counter::ShadowRays);
+        let mut occluder: Hit = Default::default();
+        match scene.hit(
+            &Ray::new(rec.p, direction),
+            0.0001,
+            self.distance,
+            &mut occluder,
+        ) {
+            true => Colour::default(),
+            false => Colour::new(1, 1, 1),
+        }
+    }
+}
+
+/// The shading normal at the first hit, mapped from [-1, 1] to [0, 1].
+#[derive(Debug)]
+pub struct Normals;
+
+impl Integrator for Normals {
+    fn colour(&self, ray: &Ray, scene: &Scene, _depths: &PathDepths, film: &mut Film) -> Colour {
+        let mut rec: Hit = Default::default();
+        match film.trace_camera_ray(scene, ray, &mut rec) {
+            true => 0.5 * (rec.normal + Colour::new(1, 1, 1)),
+            false => Colour::default(),
+        }
+    }
+}
+
+/// The texture coordinates at the first hit as red and green.
+#[derive(Debug)]
+pub struct Uvs;
+
+impl Integrator for Uvs {
+    fn colour(&self, ray: &Ray, scene: &Scene, _depths: &PathDepths, film: &mut Film) -> Colour {
+        let mut rec: Hit = Default::default();
+        match film.trace_camera_ray(scene, ray, &mut rec) {
+            true => Colour::new(rec.u, rec.v, 0),
+            false => Colour::default(),
+        }
+    }
+}
+
+/// Distance to the first hit in false colour, from blue up close to red at
+/// `far` and beyond.
+#[derive(Debug)]
+pub struct Depth {
+    far: f64,
+}
+
+impl Depth {
+    pub fn new(far: f64) -> Self {
+        Self { far }
+    }
+}
+
+impl Integrator for Depth {
+    fn colour(&self, ray: &Ray, scene: &Scene, _depths: &PathDepths, film: &mut Film) -> Colour {
+        let mut rec: Hit = Default::default();
+        match film.trace_camera_ray(scene, ray, &mut rec) {
+            true => false_colour(rec.t * ray.direction().length() / self.far),
+            false => Colour::default(),
+        }
+    }
+}
+
+/// A different colour for each material instance, picked from the order
+/// objects first use them so that every run of a scene agrees.
+#[derive(Debug)]
+pub struct MaterialIds;
+
+impl Integrator for MaterialIds {
+    fn colour(&self, ray: &Ray, scene: &Scene, _depths: &PathDepths, film: &mut Film) -> Colour {
+        let mut rec: Hit = Default::default();
+        if !film.trace_camera_ray(scene, ray, &mut rec) {
+            return Colour::default();
+        }
+        match rec.material.as_ref().and_then(|mat| scene.material_id(mat)) {
+            Some(id) => {
+                let hash = (id as u64 + 1).wrapping_mul(0x9e3779b97f4a7c15);
+                false_colour((hash >> 11) as f64 / (1u64 << 53) as f64)
+            }
+            None => Colour::default(),
+        }
+    }
+}
+
+/// How much work finding the first hit took, counting intersection tests and
+/// BVH node visits, in false colour on a log scale from blue for a single
+/// test up to red for visiting every node and testing every object.
+#[derive(Debug)]
+pub struct TraversalCost;
+
+impl Integrator for TraversalCost {
+    fn colour(&self, ray: &Ray, scene: &Scene, _depths: &PathDepths, film: &mut Film) -> Colour {
+        let work = || local_count(Counter::IntersectionTests) + local_count(Counter::BvhNodeVisits);
+        let before = work();
+        let mut rec: Hit = Default::default();
+        film.trace_camera_ray(scene, ray, &mut rec);
+        let cost = (work() - before) as f64;
+        // A tree over n objects has 2n - 1 nodes.
+        let most = (3 * scene.world().len()).max(2) as f64;
+        false_colour(cost.max(1.).ln() / most.ln())
+    }
+}
+
+/// Maps `value` in [0, 1] onto a blue, cyan, green, yellow, red ramp.
+fn false_colour(value: f64) -> Colour {
+    let value = value.clamp(0., 1.);
+    let ramp = |centre: f64| (1.5 - (4. * value - centre).abs()).clamp(0., 1.);
+    Colour::new(ramp(3.), ramp(2.), ramp(1.))
+}
+
+/// Light given off by the surface that was hit, weighted against the chance
+/// that a shadow ray would have found it instead.
+fn emitted_light(
+    scene: &Scene,
+    ray: &Ray,
+    mat: &dyn Material,
+    rec: &Hit,
+    scatter_pdf: f64,
+) -> Colour {
+    let emitted = mat.emitted(rec);
+    match scatter_pdf > 0. && !emitted.near_zero() {
+        true => {
+            let light_pdf = scene.emitters().pdf(&ray.origin(), &rec.p);
+            emitted * power_heuristic(scatter_pdf, light_pdf)
+        }
+        false => emitted,
+    }
+}
+
+/// Light reaching the hit directly from the scene's lights, one of its
+/// emissive objects and any bright spots in the environment, found by tracing
+/// shadow rays towards them.
+fn direct_light(scene: &Scene, ray: &Ray, mat: &dyn Material, rec: &Hit) -> Colour {
+    punctual_light(scene, ray, mat, rec)
+        + emitter_light(scene, ray, mat, rec)
+        + environment_shadow_light(scene, ray, mat, rec)
+}
+
+/// Light from the lights that aren't part of the geometry, which only shadow
+/// rays can find.
+pub(crate) fn punctual_light(scene: &Scene, ray: &Ray, mat: &dyn Material, rec: &Hit) -> Colour {
+    let mut direct = Colour::default();
+    for light in scene.lights() {
+        let Some(sample) = light.sample(&rec.p) else {
+            continue;
+        };
+        let response = mat.bsdf(ray, rec, &sample.direction);
+        if response.near_zero() {
+            continue;
+        }
+        count(Counter::ShadowRays);
+        let shadow_ray = Ray::new(rec.p, sample.direction);
+        let mut shadow_rec: Hit = Default::default();
+        if !scene.hit(&shadow_ray, 0.0001, sample.distance, &mut shadow_rec) {
+            direct += response * sample.irradiance;
+        }
+    }
+    direct
+}
+
+/// Light from one of the emissive objects, weighted against the chance that a
+/// scattered ray would have found it instead.
+fn emitter_light(scene: &Scene, ray: &Ray, mat: &dyn Material, rec: &Hit) -> Colour {
+    let Some(sample) = scene.emitters().sample(&rec.p) else {
+        return Colour::default();
+    };
+    let response = mat.bsdf(ray, rec, &sample.direction);
+    if response.near_zero() || sample.distance <= 0.0001 {
+        return Colour::default();
+    }
+    count(Counter::ShadowRays);
+    let shadow_ray = Ray::new(rec.p, sample.direction);
+    let mut shadow_rec: Hit = Default::default();
+    match scene.hit(
+        &shadow_ray,
+        0.0001,
+        sample.distance - 0.0001,
+        &mut shadow_rec,
+    ) {
+        true => Colour::default(),
+        false => {
+            let weight = power_heuristic(sample.pdf, mat.pdf(ray, rec, &sample.direction));
+            response * sample.radiance * weight / sample.pdf
+        }
+    }
+}
+
+/// Light from a bright spot in the environment, weighted against the chance
+/// that a scattered ray would have found it instead.
+pub(crate) fn environment_shadow_light(
+    scene: &Scene,
+    ray: &Ray,
+    mat: &dyn Material,
+    rec: &Hit,
+) -> Colour {
+    let environment = scene.environment();
+    let Some((direction, light_pdf)) = environment.sample() else {
+        return Colour::default();
+    };
+    let response = mat.bsdf(ray, rec, &direction);
+    if response.near_zero() {
+        return Colour::default();
+    }
+    count(Counter::ShadowRays);
+    let shadow_ray = Ray::new(rec.p, direction);
+    let mut shadow_rec: Hit = Default::default();
+    match scene.hit(&shadow_ray, 0.0001, f64::INFINITY, &mut shadow_rec) {
+        true => Colour::default(),
+        false => {
+            let weight = power_heuristic(light_pdf, mat.pdf(ray, rec, &direction));
+            response * environment.radiance(&direction) * weight / light_pdf
+        }
+    }
+}
+
+/// Light from the environment, weighted against the chance that a shadow ray
+/// would have found it instead.
+pub(crate) fn environment_light(scene: &Scene, ray: &Ray, scatter_pdf: f64) -> Colour {
+    let environment = scene.environment();
+    let radiance = environment.radiance(&ray.direction());
+    match scatter_pdf > 0. {
+        true => radiance * power_heuristic(scatter_pdf, environment.pdf(&ray.direction())),
+        false => radiance,
+    }
+}
+
+/// Russian roulette: the chance a path carrying `throughput` carries on, or
+/// `None` if it has been ended.
+pub(crate) fn survival_probability(throughput: f64) -> Option<f64> {
+    let survival = throughput.min(1.);
+    match thread_rng().gen::<f64>() < survival {
+        true => Some(survival),
+        false => {
+            count(Counter::RouletteTerminations);
+            None
+        }
+    }
+}
+
+/// Veach's power heuristic for combining two ways of sampling a direction.
+fn power_heuristic(pdf: f64, other_pdf: f64) -> f64 {
+    let (pdf2, other_pdf2) = (pdf * pdf, other_pdf * other_pdf);
+    match pdf2 + other_pdf2 > 0. {
+        true => pdf2 / (pdf2 + other_pdf2),
+        false => 0.,
+    }
+}
@@ -0,0 +1,423 @@
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::camera::Camera;
+use crate::framebuffer::{FrameBuffer, PixelSamples};
+use crate::integrator::{Film, Integrator, PathDepths, Splat};
+use crate::scene::Scene;
+use crate::settings::RenderSettings;
+use crate::spectrum::Wavelengths;
+use crate::stats::{count, Budget, Counter, Stats};
+
+use rand::prelude::*;
+
+const PREVIEW_BLOCK_SIZE: u32 = 8;
+const SAMPLES_PER_PASS: u32 = 4;
+/// Adaptive renders stop here even if the target error hasn't been reached,
+/// since fireflies and emitters may never settle.
+const MAX_ADAPTIVE_SAMPLES: f64 = 4096.;
+/// How far apart the rows a pass visits one after another are, so that a pass
+/// cut short leaves its missing samples spread over the image.
+const ROW_STRIDE: usize = 16;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum JobKind {
+    /// Traces a single sample per block of pixels so the image can follow the
+    /// camera while it is moving.
+    Preview,
+    /// Replaces whatever is in the framebuffer with a full render.
+    Full,
+    /// Tops up each pixel to the full sample count, keeping the samples already
+    /// in the framebuffer.
+    Continue,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum JobState {
+    Running,
+    Paused,
+    Cancelled,
+}
+
+struct JobControl {
+    state: Mutex<JobState>,
+    changed: Condvar,
+}
+
+impl JobControl {
+    fn set(&self, state: JobState) {
+        *self
+            .state
+            .lock()
+            .expect("Something went wrong inside the job lock") = state;
+        self.changed.notify_all();
+    }
+
+    fn get(&self) -> JobState {
+        *self
+            .state
+            .lock()
+            .expect("Something went wrong inside the job lock")
+    }
+
+    /// Blocks while the job is paused, returning false once it has been cancelled.
+    fn proceed(&self) -> bool {
+        let mut state = self
+            .state
+            .lock()
+            .expect("Something went wrong inside the job lock");
+        while *state == JobState::Paused {
+            state = self
+                .changed
+                .wait(state)
+                .expect("Something went wrong inside the job lock");
+        }
+        *state == JobState::Running
+    }
+}
+
+/// Traces the samples for individual pixels of an image.
+pub struct PixelTracer<'a> {
+    pub scene: &'a Scene,
+    pub camera: &'a Camera,
+    pub width: u32,
+    pub height: u32,
+    pub integrator: &'a dyn Integrator,
+    pub depths: PathDepths,
+    pub seed: u64,
+    pub spectral: bool,
+}
+
+impl PixelTracer<'_> {
+    /// Traces `samples` samples for a pixel, starting from sample number
+    /// `first_sample`, along with any light those samples sent to other pixels.
+    pub fn trace(
+        &self,
+        x: u32,
+        y: u32,
+        first_sample: u32,
+        samples: u32,
+    ) -> (PixelSamples, Vec<Splat>) {
+        let mut rng = Self::pixel_rng(self.seed, x, y, first_sample);
+        let mut pixel_samples = PixelSamples::default();
+        let mut film = Film::new(self.camera, self.width, self.height);
+        for _ in 0..samples {
+            let u = (x as f64 + rng.gen::<f64>()) / (self.width - 1) as f64;
+            let v = (y as f64 + rng.gen::<f64>()) / (self.height - 1) as f64;
+            let ray = match self.spectral {
+                true => self
+                    .camera
+                    .get_ray(u, v)
+                    .with_wavelengths(Wavelengths::sample(rng.gen())),
+                false => self.camera.get_ray(u, v),
+            };
+            count(Counter::CameraRays);
+            let colour = self
+                .integrator
+                .colour(&ray, self.scene, &self.depths, &mut film);
+            // Integrators record the features of their first hit; only ones
+            // that don't need the ray tracing again.
+            let features = film
+                .take_features()
+                .unwrap_or_else(|| ray.features(self.scene));
+            pixel_samples.add_sample(colour, &features);
+        }
+        (pixel_samples, film.into_splats())
+    }
+
+    /// Seeds the positions of a pixel's samples from the render seed, so a
+    /// resumed render doesn't repeat the positions it has already used. Only
+    /// these positions are seeded: lens, material and light sampling draw from
+    /// each thread's own generator, so no two renders are quite the same.
+    fn pixel_rng(seed: u64, x: u32, y: u32, first_sample: u32) -> StdRng {
+        let pixel = ((y as u64) << 32 | x as u64).wrapping_mul(0x9e3779b97f4a7c15);
+        StdRng::seed_from_u64(seed ^ pixel ^ (first_sample as u64).rotate_left(48))
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Pass {
+    /// Overwrites each block of pixels with `samples` new samples.
+    Replace { samples: u32, block_size: u32 },
+    /// Brings each pixel up to `samples` samples.
+    TopUp { samples: u32 },
+    /// Adds `samples` more samples to each pixel.
+    Add { samples: u32 },
+}
+
+struct PassContext<'a> {
+    tracer: PixelTracer<'a>,
+    framebuffer_ref: &'a Mutex<FrameBuffer>,
+    control: &'a JobControl,
+    stats: &'a Stats,
+    time_limit: Option<Duration>,
+}
+
+impl PassContext<'_> {
+    /// Renders this thread's share of the rows, returning false if the pass was
+    /// cut short by a cancellation or, if it is `timed`, by running out of time.
+    fn render_rows(&self, pass: Pass, timed: bool, thread_id: usize, thread_count: usize) -> bool {
+        let (width, height) = (self.tracer.width, self.tracer.height);
+        let block_size = match pass {
+            Pass::Replace { block_size, .. } => block_size,
+            Pass::TopUp { .. } | Pass::Add { .. } => 1,
+        };
+
+        for y in row_order(height, block_size)
+            .into_iter()
+            .skip(thread_id)
+            .step_by(thread_count)
+        {
+            let mut pixels_done = 0;
+            for x in (0..width).step_by(block_size as usize) {
+                let out_of_time = timed
+                    && self
+                        .time_limit
+                        .is_some_and(|limit| self.stats.elapsed() >= limit);
+                if out_of_time || !self.control.proceed() {
+                    self.stats.flush(pixels_done);
+                    return false;
+                }
+                pixels_done += 1;
+
+                let samples_done = self.lock_framebuffer().samples_at(x, y);
+                let (first_sample, samples) = match pass {
+                    Pass::Replace { samples, .. } => (0, samples),
+                    Pass::TopUp { samples } => (samples_done, samples.saturating_sub(samples_done)),
+                    Pass::Add { samples } => (samples_done, samples),
+                };
+                if samples == 0 {
+                    continue;
+                }
+
+                let (pixel_samples, splats) = self.tracer.trace(x, y, first_sample, samples);
+                let mut framebuffer = self.lock_framebuffer();
+                framebuffer.count_camera_samples(samples);
+                for splat in splats.iter() {
+                    framebuffer.add_splat(splat.x, splat.y, &splat.colour);
+                }
+                match pass {
+                    Pass::Replace { .. } => {
+                        for block_y in y..(y + block_size).min(height) {
+                            for block_x in x..(x + block_size).min(width) {
+                                framebuffer.set_samples(block_x, block_y, &pixel_samples);
+                            }
+                        }
+                    }
+                    Pass::TopUp { .. } | Pass::Add { .. } => {
+                        framebuffer.add_samples(x, y, &pixel_samples)
+                    }
+                }
+            }
+            self.stats.flush(pixels_done);
+        }
+        true
+    }
+
+    fn lock_framebuffer(&self) -> MutexGuard<'_, FrameBuffer> {
+        self.framebuffer_ref
+            .lock()
+            .expect("Something went wrong inside the framebuffer lock")
+    }
+}
+
+/// The rows of blocks a pass visits, from the top of the image down but taking
+/// every `ROW_STRIDE`th row on each sweep.
+fn row_order(height: u32, block_size: u32) -> Vec<u32> {
+    let rows: Vec<u32> = (0..height).rev().filter(|y| y % block_size == 0).collect();
+    let stride = ROW_STRIDE.min(rows.len()).max(1);
+    (0..stride)
+        .flat_map(|offset| rows.iter().skip(offset).step_by(stride).copied())
+        .collect()
+}
+
+/// A handle to the threads rendering one image into a framebuffer.
+pub struct RenderJob {
+    control: Arc<JobControl>,
+    stats: Arc<Stats>,
+    thread: JoinHandle<()>,
+    kind: JobKind,
+}
+
+impl RenderJob {
+    /// Starts rendering `camera`'s view of the scene into the framebuffer.
+    ///
+    /// With a time limit or target error in the settings, every pixel gets one
+    /// sample however short the time, then the image is refined in passes of
+    /// `SAMPLES_PER_PASS` samples until the budget is used up or the pixels
+    /// average `MAX_ADAPTIVE_SAMPLES` samples.
+    pub fn start(
+        framebuffer_ref: Arc<Mutex<FrameBuffer>>,
+        scene: Scene,
+        camera: Camera,
+        settings: RenderSettings,
+        kind: JobKind,
+    ) -> Self {
+        let (width, height) = {
+            let framebuffer = framebuffer_ref
+                .lock()
+                .expect("Something went wrong inside the framebuffer lock");
+            (framebuffer.width(), framebuffer.height())
+        };
+        let adaptive = kind != JobKind::Preview && settings.is_adaptive();
+        let first_pass = match (kind, adaptive) {
+            (JobKind::Preview, _) => Pass::Replace {
+                samples: 1,
+                block_size: PREVIEW_BLOCK_SIZE,
+            },
+            (JobKind::Full, false) => Pass::Replace {
+                samples: settings.samples_per_pixel,
+                block_size: 1,
+            },
+            (JobKind::Continue, false) => Pass::TopUp {
+                samples: settings.samples_per_pixel,
+            },
+            (JobKind::Full, true) => Pass::Replace {
+                samples: 1,
+                block_size: 1,
+            },
+            (JobKind::Continue, true) => Pass::Add { samples: 1 },
+        };
+
+        let control = Arc::new(JobControl {
+            state: Mutex::new(JobState::Running),
+            changed: Condvar::new(),
+        });
+        let budget = match (adaptive, settings.time_limit) {
+            (true, Some(time_limit)) => Budget::Time(time_limit),
+            (true, None) => Budget::OpenEnded,
+            (false, _) => {
+                let block_size = match first_pass {
+                    Pass::Replace { block_size, .. } => block_size,
+                    Pass::TopUp { .. } | Pass::Add { .. } => 1,
+                };
+                Budget::Pixels((width.div_ceil(block_size) * height.div_ceil(block_size)) as u64)
+            }
+        };
+        let stats = Arc::new(Stats::new(budget, (width * height) as u64));
+
+        let control_local = control.clone();
+        let stats_local = stats.clone();
+        let thread = thread::spawn(move || {
+            if let Pass::Replace { .. } = first_pass {
+                framebuffer_ref
+                    .lock()
+                    .expect("Something went wrong inside the framebuffer lock")
+                    .clear_splats();
+            }
+            let integrator = settings.integrator.build();
+            let context = PassContext {
+                tracer: PixelTracer {
+                    scene: &scene,
+                    camera: &camera,
+                    width,
+                    height,
+                    integrator: integrator.as_ref(),
+                    depths: settings.path_depths(),
+                    seed: settings.seed,
+                    spectral: settings.spectral,
+                },
+                framebuffer_ref: &framebuffer_ref,
+                control: &control_local,
+                stats: &stats_local,
+                time_limit: settings.time_limit.filter(|_| adaptive),
+            };
+
+            let thread_count = settings.threads.max(1);
+            let mut pass = first_pass;
+            let mut timed = false;
+            loop {
+                let completed = thread::scope(|scope| {
+                    let handles: Vec<_> = (0..thread_count)
+                        .map(|thread_id| {
+                            let context = &context;
+                            scope.spawn(move || {
+                                context.render_rows(pass, timed, thread_id, thread_count)
+                            })
+                        })
+                        .collect();
+                    handles.into_iter().all(|handle| {
+                        handle
+                            .join()
+                            .expect("Something went wrong inside a render thread")
+                    })
+                });
+                if !completed || !adaptive {
+                    break;
+                }
+
+                let (target_reached, samples_capped) = {
+                    let framebuffer = context.lock_framebuffer();
+                    (
+                        settings.target_error.is_some_and(|target_error| {
+                            framebuffer
+                                .mean_relative_error()
+                                .is_some_and(|error| error <= target_error)
+                        }),
+                        framebuffer.mean_samples_per_pixel() >= MAX_ADAPTIVE_SAMPLES,
+                    )
+                };
+                if target_reached || samples_capped {
+                    break;
+                }
+                pass = Pass::Add {
+                    samples: SAMPLES_PER_PASS,
+                };
+                timed = true;
+            }
+        });
+
+        Self {
+            control,
+            stats,
+            thread,
+            kind,
+        }
+    }
+
+    pub fn stats(&self) -> &Arc<Stats> {
+        &self.stats
+    }
+
+    pub fn is_preview(&self) -> bool {
+        self.kind == JobKind::Preview
+    }
+
+    pub fn state(&self) -> JobState {
+        self.control.get()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    pub fn pause(&self) {
+        if self.state() == JobState::Running {
+            self.stats.pause();
+            self.control.set(JobState::Paused);
+        }
+    }
+
+    pub fn resume(&self) {
+        if self.state() == JobState::Paused {
+            self.stats.resume();
+            self.control.set(JobState::Running);
+        }
+    }
+
+    /// Asks the worker threads to stop after their current pixel.
+    pub fn cancel(&self) {
+        self.stats.resume();
+        self.control.set(JobState::Cancelled);
+    }
+
+    /// Waits for the worker threads to stop, returning the job's statistics.
+    pub fn wait(self) -> Arc<Stats> {
+        self.thread
+            .join()
+            .expect("Something went wrong inside a render thread");
+        self.stats.finish();
+        self.stats
+    }
+}
@@ -1,25 +1,93 @@
-mod camera;
+//! A path tracer following the Ray Tracing in One Weekend books.
+//!
+//! ```no_run
+//! use std::path::Path;
+//! use std::sync::Arc;
+//!
+//! use rtiow::materials::lambertian::Lambertian;
+//! use rtiow::vector3::{Colour, Point3};
+//! use rtiow::{Camera, RenderSettings, Renderer, Scene};
+//!
+//! let scene = Scene::builder()
+//!     .sphere(Point3::new(0, -100.5, -1), 100, Arc::new(Lambertian::new(Colour::new(0.8, 0.8, 0.))))
+//!     .sphere(Point3::new(0, 0, -1), 0.5, Arc::new(Lambertian::new(Colour::new(0.1, 0.2, 0.5))))
+//!     .build();
+//! let camera = Camera::builder()
+//!     .look_from(Point3::new(0, 0, 1))
+//!     .look_at(Point3::new(0, 0, -1))
+//!     .v_fov(60.)
+//!     .aperture(0.)
+//!     .focus_dist(2.);
+//! let settings = RenderSettings {
+//!     width: 400,
+//!     height: 225,
+//!     samples_per_pixel: 50,
+//!     ..Default::default()
+//! };
+//!
+//! let image = Renderer::new(settings)
+//!     .camera(camera)
+//!     .on_progress(|stats| eprintln!("{}", stats.lines()[0]))
+//!     .render(&scene);
+//! image.write_ppm(Path::new("spheres.ppm")).unwrap();
+//! ```
+
+pub mod bdpt;
+pub mod bvh;
+pub mod camera;
+#[cfg(feature = "viewer")]
 mod canvas;
-mod hit;
-mod materials;
-mod ray;
+pub mod checkpoint;
+pub mod denoiser;
+pub mod distributed;
+pub mod environment;
+pub mod framebuffer;
+pub mod hit;
+pub mod image;
+pub mod integrator;
+pub mod job;
+pub mod light;
+pub mod light_tree;
+pub mod materials;
+#[cfg(feature = "viewer")]
+mod navigation;
+mod options;
+pub mod picking;
+pub mod ray;
 mod renderer;
-mod scene;
-mod sphere;
-mod vector3;
+pub mod scene;
+pub mod settings;
+pub mod spectrum;
+pub mod sphere;
+pub mod stats;
+pub mod texture;
+pub mod triangle;
+pub mod vector3;
+#[cfg(feature = "viewer")]
+mod viewer;
+mod wire;
 
-mod prelude {
+pub mod prelude {
+    pub use crate::environment::{Environment, EnvironmentMap, Gradient, Sky, Uniform};
+    pub use crate::light::{DirectionalLight, Light, PointLight, SpotLight};
     pub use crate::materials::{
+        bump::{BumpMapped, NormalMapped},
+        conductor::Conductor,
         dielectric::Dielectric,
         lambertian::Lambertian,
         material::{
-            Material,
+            BounceKind, Material,
             ScatterResult::{self, Absorbed, Scattered},
         },
         metal::Metal,
+        mix::MixMaterial,
+        plastic::Plastic,
+        principled::Principled,
+        subsurface::Subsurface,
     };
     pub use crate::ray::Ray;
     pub use crate::sphere::Sphere;
+    pub use crate::triangle::Triangle;
     pub use crate::vector3::{Colour, Point3, Vector3};
     pub use crate::{
         hit::{Hit, Hittable},
@@ -27,7 +95,17 @@ mod prelude {
     };
 }
 
-pub use crate::renderer::run;
+pub use crate::camera::{Camera, CameraSettings};
+pub use crate::distributed::run_worker;
+pub use crate::image::Image;
+pub use crate::integrator::{Integrator, IntegratorKind};
+pub use crate::options::Options;
+pub use crate::renderer::{render_headless, Renderer};
+pub use crate::scene::{Scene, SceneBuilder};
+pub use crate::settings::RenderSettings;
+pub use crate::stats::Stats;
+#[cfg(feature = "viewer")]
+pub use crate::viewer::run;
 
 #[macro_use]
 extern crate auto_ops;
@@ -0,0 +1,173 @@
+use std::f64::consts::PI;
+use std::fmt::Debug;
+
+use crate::materials::microfacet::Frame;
+use crate::prelude::*;
+
+use rand::{thread_rng, Rng};
+
+/// Light arriving at a point from one of the scene's lights.
+pub struct LightSample {
+    /// Unit vector from the point towards the light.
+    pub direction: Vector3,
+    /// How far away the light is, so shadow rays stop short of it.
+    pub distance: f64,
+    /// The light arriving along `direction`, ready to be multiplied by the
+    /// surface's response.
+    pub irradiance: Colour,
+}
+
+/// A light that isn't part of the geometry, so rays can never hit it and it
+/// only contributes through shadow rays.
+pub trait Light: Send + Sync + Debug {
+    /// Picks the light arriving at `point`, or `None` if it can't reach it.
+    fn sample(&self, point: &Point3) -> Option<LightSample>;
+}
+
+/// A light at a single point, shining equally in every direction and
+/// falling off with the square of the distance.
+#[derive(Debug)]
+pub struct PointLight {
+    position: Point3,
+    intensity: Colour,
+}
+
+impl PointLight {
+    pub fn new(position: Point3, intensity: Colour) -> Self {
+        Self {
+            position,
+            intensity,
+        }
+    }
+}
+
+impl Light for PointLight {
+    fn sample(&self, point: &Point3) -> Option<LightSample> {
+        let to_light = self.position - point;
+        let distance_squared = to_light.length_squared();
+        let distance = distance_squared.sqrt();
+        Some(LightSample {
+            direction: to_light / distance,
+            distance,
+            irradiance: self.intensity / distance_squared,
+        })
+    }
+}
+
+/// A point light that only shines within a cone, fading smoothly from full
+/// brightness inside `falloff_start` to nothing at `cone_angle`.
+#[derive(Debug)]
+pub struct SpotLight {
+    position: Point3,
+    direction: Vector3,
+    intensity: Colour,
+    cos_cone_angle: f64,
+    cos_falloff_start: f64,
+}
+
+impl SpotLight {
+    /// Both angles are in degrees, measured from the centre of the beam.
+    pub fn new(
+        position: Point3,
+        target: Point3,
+        intensity: Colour,
+        cone_angle: f64,
+        falloff_start: f64,
+    ) -> Self {
+        Self {
+            position,
+            direction: (target - position).unit_vector(),
+            intensity,
+            cos_cone_angle: cone_angle.to_radians().cos(),
+            cos_falloff_start: falloff_start.min(cone_angle).to_radians().cos(),
+        }
+    }
+
+    fn falloff(&self, cos_theta: f64) -> f64 {
+        if cos_theta >= self.cos_falloff_start {
+            return 1.;
+        }
+        let t = ((cos_theta - self.cos_cone_angle)
+            / (self.cos_falloff_start - self.cos_cone_angle))
+            .clamp(0., 1.);
+        t * t * (3. - 2. * t)
+    }
+}
+
+impl Light for SpotLight {
+    fn sample(&self, point: &Point3) -> Option<LightSample> {
+        let to_light = self.position - point;
+        let distance_squared = to_light.length_squared();
+        let distance = distance_squared.sqrt();
+        let direction = to_light / distance;
+        let falloff = self.falloff(-direction.dot(&self.direction));
+        match falloff > 0. {
+            true => Some(LightSample {
+                direction,
+                distance,
+                irradiance: falloff * self.intensity / distance_squared,
+            }),
+            false => None,
+        }
+    }
+}
+
+/// A light so far away that it shines in one direction everywhere, like the
+/// sun. Giving it an angular diameter spreads its direction over a small
+/// disc, which softens the shadows it casts.
+#[derive(Debug)]
+pub struct DirectionalLight {
+    /// Points from the scene towards the light.
+    direction: Vector3,
+    irradiance: Colour,
+    cos_angular_radius: f64,
+}
+
+impl DirectionalLight {
+    /// `direction` points from the scene towards the light, and `irradiance`
+    /// is the light falling on a surface facing it.
+    pub fn new(direction: Vector3, irradiance: Colour) -> Self {
+        Self {
+            direction: direction.unit_vector(),
+            irradiance,
+            cos_angular_radius: 1.,
+        }
+    }
+
+    /// The apparent size of the light in degrees; the sun is about 0.53.
+    pub fn with_angular_diameter(mut self, angular_diameter: f64) -> Self {
+        self.cos_angular_radius = (0.5 * angular_diameter).to_radians().cos();
+        self
+    }
+}
+
+impl Light for DirectionalLight {
+    fn sample(&self, _point: &Point3) -> Option<LightSample> {
+        Some(LightSample {
+            direction: sample_cone(&self.direction, self.cos_angular_radius),
+            distance: f64::INFINITY,
+            irradiance: self.irradiance,
+        })
+    }
+}
+
+/// Picks a direction uniformly within the cone around `axis` whose half angle
+/// has the given cosine. The pdf is `1 / cone_solid_angle(cos_max)`.
+pub fn sample_cone(axis: &Vector3, cos_max: f64) -> Vector3 {
+    if cos_max >= 1. {
+        return *axis;
+    }
+    let mut rng = thread_rng();
+    let cos_theta = 1. - rng.gen::<f64>() * (1. - cos_max);
+    let sin_theta = (1. - cos_theta * cos_theta).sqrt();
+    let phi = 2. * PI * rng.gen::<f64>();
+    Frame::from_normal(axis).to_world(&Vector3::new(
+        sin_theta * phi.cos(),
+        sin_theta * phi.sin(),
+        cos_theta,
+    ))
+}
+
+pub fn cone_solid_angle(cos_max: f64) -> f64 {
+    2. * PI * (1. - cos_max)
+}
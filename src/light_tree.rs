@@ -0,0 +1,361 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+use crate::light::{cone_solid_angle, sample_cone};
+use crate::prelude::*;
+
+use rand::{thread_rng, Rng};
+
+/// A sphere whose material gives off light, which shadow rays can be aimed
+/// at directly instead of waiting for scattered rays to find it.
+#[derive(Debug)]
+pub struct Emitter {
+    sphere: Sphere,
+    center: Point3,
+    radius: f64,
+    /// Estimated total light given off, for deciding how often to pick it.
+    power: f64,
+}
+
+impl Emitter {
+    pub fn new(center: Point3, radius: f64, material: Arc<dyn Material>) -> Self {
+        let power = material.average_emission().luminance() * 4. * PI * radius * radius * PI;
+        Self {
+            sphere: Sphere::new(center, radius, material),
+            center,
+            radius,
+            power,
+        }
+    }
+
+    /// The cosine of the half angle the sphere covers as seen from `point`,
+    /// or `None` from inside it.
+    fn cos_max(&self, point: &Point3) -> Option<f64> {
+        let distance_squared = (self.center - point).length_squared();
+        let radius_squared = self.radius * self.radius;
+        match distance_squared > radius_squared {
+            true => Some((1. - radius_squared / distance_squared).sqrt()),
+            false => None,
+        }
+    }
+
+    fn sample(&self, point: &Point3) -> Option<EmitterSample> {
+        let cos_max = self.cos_max(point)?;
+        let axis = (self.center - point).unit_vector();
+        let direction = sample_cone(&axis, cos_max);
+        let mut rec: Hit = Default::default();
+        if !self
+            .sphere
+            .hit(&Ray::new(*point, direction), 0., f64::INFINITY, &mut rec)
+        {
+            return None;
+        }
+        let radiance = rec.material.as_ref()?.emitted(&rec);
+        Some(EmitterSample {
+            direction,
+            distance: rec.t,
+            radiance,
+            pdf: 1. / cone_solid_angle(cos_max),
+        })
+    }
+
+    fn area(&self) -> f64 {
+        4. * PI * self.radius * self.radius
+    }
+
+    /// A point spread evenly over the sphere, with the hit a ray arriving
+    /// there from outside would have found.
+    fn sample_surface(&self) -> Option<Hit> {
+        let normal = Vector3::random_in_unit_sphere().unit_vector();
+        let ray = Ray::new(self.center + 2. * self.radius * normal, -normal);
+        let mut rec: Hit = Default::default();
+        match self.sphere.hit(&ray, 0., f64::INFINITY, &mut rec) {
+            true => Some(rec),
+            false => None,
+        }
+    }
+
+    fn contains(&self, light_point: &Point3) -> bool {
+        ((*light_point - self.center).length() - self.radius).abs() <= 1e-6 * self.radius.max(1.)
+    }
+
+    fn pdf(&self, point: &Point3) -> f64 {
+        match self.cos_max(point) {
+            Some(cos_max) => 1. / cone_solid_angle(cos_max),
+            None => 0.,
+        }
+    }
+}
+
+/// A point on an emitter for light paths to start from.
+pub struct EmissionSample {
+    /// The surface at the point, facing outwards.
+    pub rec: Hit,
+    /// The pdf per unit area of choosing the point, including the chance of
+    /// picking its emitter.
+    pub pdf: f64,
+}
+
+/// Light arriving at a point from a sampled emitter.
+pub struct EmitterSample {
+    /// Unit vector from the point towards the emitter.
+    pub direction: Vector3,
+    pub distance: f64,
+    pub radiance: Colour,
+    /// The pdf per unit solid angle of choosing this direction, including
+    /// the chance of picking this emitter.
+    pub pdf: f64,
+}
+
+#[derive(Debug)]
+struct Node {
+    min: Point3,
+    max: Point3,
+    power: f64,
+    kind: NodeKind,
+}
+
+#[derive(Debug)]
+enum NodeKind {
+    Leaf(usize),
+    Interior(usize, usize),
+}
+
+/// A bounding volume hierarchy over the scene's emitters that picks one in
+/// proportion to a rough estimate of the light it sends to a point, so that
+/// nearby and bright emitters are sampled far more often than distant or dim
+/// ones however many there are.
+#[derive(Debug, Default)]
+pub struct LightTree {
+    emitters: Vec<Emitter>,
+    nodes: Vec<Node>,
+}
+
+impl LightTree {
+    /// Collects the emitters among `world`'s objects.
+    pub fn new(world: &[Box<dyn Hittable>]) -> Self {
+        let mut tree = Self {
+            emitters: world.iter().filter_map(|object| object.emitter()).collect(),
+            nodes: vec![],
+        };
+        let mut indices: Vec<usize> = (0..tree.emitters.len()).collect();
+        if !indices.is_empty() {
+            tree.build(&mut indices);
+        }
+        tree
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.emitters.is_empty()
+    }
+
+    /// Adds the nodes for a set of emitters, splitting them in half along the
+    /// axis their centres spread furthest, and returns the index of the top.
+    fn build(&mut self, indices: &mut [usize]) -> usize {
+        if let [index] = indices {
+            let emitter = &self.emitters[*index];
+            let extent = Vector3::new(emitter.radius, emitter.radius, emitter.radius);
+            self.nodes.push(Node {
+                min: emitter.center - extent,
+                max: emitter.center + extent,
+                power: emitter.power,
+                kind: NodeKind::Leaf(*index),
+            });
+            return self.nodes.len() - 1;
+        }
+
+        let centers: Vec<Point3> = indices
+            .iter()
+            .map(|index| self.emitters[*index].center)
+            .collect();
+        let axis_value = |point: &Point3, axis: usize| match axis {
+            0 => point.x(),
+            1 => point.y(),
+            _ => point.z(),
+        };
+        let spread = |axis: usize| {
+            let values = centers.iter().map(|center| axis_value(center, axis));
+            values.clone().fold(f64::MIN, f64::max) - values.fold(f64::MAX, f64::min)
+        };
+        let axis = (0..3)
+            .max_by(|a, b| spread(*a).total_cmp(&spread(*b)))
+            .unwrap_or_default();
+        indices.sort_by(|a, b| {
+            axis_value(&self.emitters[*a].center, axis)
+                .total_cmp(&axis_value(&self.emitters[*b].center, axis))
+        });
+
+        let (left_indices, right_indices) = indices.split_at_mut(indices.len() / 2);
+        let left = self.build(left_indices);
+        let right = self.build(right_indices);
+        let (left_node, right_node) = (&self.nodes[left], &self.nodes[right]);
+        self.nodes.push(Node {
+            min: Point3::new(
+                left_node.min.x().min(right_node.min.x()),
+                left_node.min.y().min(right_node.min.y()),
+                left_node.min.z().min(right_node.min.z()),
+            ),
+            max: Point3::new(
+                left_node.max.x().max(right_node.max.x()),
+                left_node.max.y().max(right_node.max.y()),
+                left_node.max.z().max(right_node.max.z()),
+            ),
+            power: left_node.power + right_node.power,
+            kind: NodeKind::Interior(left, right),
+        });
+        self.nodes.len() - 1
+    }
+
+    fn root(&self) -> Option<&Node> {
+        self.nodes.last()
+    }
+
+    /// How much light a node might send to `point`: its power over the
+    /// squared distance to its centre, capped for points close to or inside
+    /// its bounds.
+    fn importance(node: &Node, point: &Point3) -> f64 {
+        let center = 0.5 * (node.min + node.max);
+        let half_diagonal_squared = (0.5 * (node.max - node.min)).length_squared();
+        node.power / (center - point).length_squared().max(half_diagonal_squared)
+    }
+
+    /// The chances of descending into each child of an interior node.
+    fn child_probabilities(&self, left: usize, right: usize, point: &Point3) -> (f64, f64) {
+        let left_importance = Self::importance(&self.nodes[left], point);
+        let right_importance = Self::importance(&self.nodes[right], point);
+        let total = left_importance + right_importance;
+        match total > 0. {
+            true => (left_importance / total, right_importance / total),
+            false => (0.5, 0.5),
+        }
+    }
+
+    /// Picks an emitter by walking down the tree, then a direction towards it.
+    pub fn sample(&self, point: &Point3) -> Option<EmitterSample> {
+        let mut node = self.root()?;
+        let mut probability = 1.;
+        let mut rng = thread_rng();
+        loop {
+            match node.kind {
+                NodeKind::Leaf(index) => {
+                    let mut sample = self.emitters[index].sample(point)?;
+                    sample.pdf *= probability;
+                    return Some(sample);
+                }
+                NodeKind::Interior(left, right) => {
+                    let (left_probability, right_probability) =
+                        self.child_probabilities(left, right, point);
+                    match rng.gen::<f64>() < left_probability {
+                        true => {
+                            probability *= left_probability;
+                            node = &self.nodes[left];
+                        }
+                        false => {
+                            probability *= right_probability;
+                            node = &self.nodes[right];
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Picks an emitter in proportion to its power, wherever the light is
+    /// going, then a point on it.
+    pub fn sample_emission(&self) -> Option<EmissionSample> {
+        let total_power = self.root()?.power;
+        if total_power <= 0. {
+            return None;
+        }
+        let mut node = self.root()?;
+        let mut rng = thread_rng();
+        loop {
+            match node.kind {
+                NodeKind::Leaf(index) => {
+                    let emitter = &self.emitters[index];
+                    return Some(EmissionSample {
+                        rec: emitter.sample_surface()?,
+                        pdf: emitter.power / total_power / emitter.area(),
+                    });
+                }
+                NodeKind::Interior(left, right) => {
+                    let left_power = self.nodes[left].power;
+                    let right_power = self.nodes[right].power;
+                    node = match rng.gen::<f64>() * (left_power + right_power) < left_power {
+                        true => &self.nodes[left],
+                        false => &self.nodes[right],
+                    };
+                }
+            }
+        }
+    }
+
+    /// The pdf per unit area of `sample_emission` choosing `light_point`.
+    pub fn emission_pdf(&self, light_point: &Point3) -> f64 {
+        match self.root() {
+            Some(root) if root.power > 0. => {
+                self.node_emission_pdf(self.nodes.len() - 1, light_point) / root.power
+            }
+            _ => 0.,
+        }
+    }
+
+    /// The power of the emitters under a node whose surface holds
+    /// `light_point`, each divided by its area.
+    fn node_emission_pdf(&self, index: usize, light_point: &Point3) -> f64 {
+        let node = &self.nodes[index];
+        if !Self::bounds_contain(node, light_point) {
+            return 0.;
+        }
+        match node.kind {
+            NodeKind::Leaf(index) => {
+                let emitter = &self.emitters[index];
+                match emitter.contains(light_point) {
+                    true => emitter.power / emitter.area(),
+                    false => 0.,
+                }
+            }
+            NodeKind::Interior(left, right) => {
+                self.node_emission_pdf(left, light_point)
+                    + self.node_emission_pdf(right, light_point)
+            }
+        }
+    }
+
+    fn bounds_contain(node: &Node, light_point: &Point3) -> bool {
+        let margin = 1e-6 * (node.max - node.min).length().max(1.);
+        let inside =
+            |value: f64, min: f64, max: f64| min - margin <= value && value <= max + margin;
+        inside(light_point.x(), node.min.x(), node.max.x())
+            && inside(light_point.y(), node.min.y(), node.max.y())
+            && inside(light_point.z(), node.min.z(), node.max.z())
+    }
+
+    /// The pdf of `sample` from `point` choosing the direction that reaches
+    /// `light_point` on the surface of an emitter.
+    pub fn pdf(&self, point: &Point3, light_point: &Point3) -> f64 {
+        match self.root() {
+            Some(_) => self.node_pdf(self.nodes.len() - 1, point, light_point),
+            None => 0.,
+        }
+    }
+
+    fn node_pdf(&self, index: usize, point: &Point3, light_point: &Point3) -> f64 {
+        let node = &self.nodes[index];
+        if !Self::bounds_contain(node, light_point) {
+            return 0.;
+        }
+        match node.kind {
+            NodeKind::Leaf(emitter) => match self.emitters[emitter].contains(light_point) {
+                true => self.emitters[emitter].pdf(point),
+                false => 0.,
+            },
+            NodeKind::Interior(left, right) => {
+                let (left_probability, right_probability) =
+                    self.child_probabilities(left, right, point);
+                left_probability * self.node_pdf(left, point, light_point)
+                    + right_probability * self.node_pdf(right, point, light_point)
+            }
+        }
+    }
+}
@@ -1,22 +1,54 @@
 extern crate rtiow;
 
+#[cfg(feature = "viewer")]
 use macroquad::prelude::*;
+use rtiow::Options;
 
 pub const ASPECT_RATIO: f64 = 3. / 2.;
-const WINDOW_WIDTH: i32 = 1200;
 
-fn window_conf() -> Conf {
-    let window_height = (WINDOW_WIDTH as f64 / ASPECT_RATIO) as i32;
+#[cfg(feature = "viewer")]
+fn window_conf(window_width: i32) -> Conf {
+    let window_height = (window_width as f64 / ASPECT_RATIO) as i32;
     Conf {
         window_title: "Raytracing in One Weekend".into(),
         window_resizable: false,
-        window_width: WINDOW_WIDTH,
+        window_width,
         window_height,
         ..Default::default()
     }
 }
 
-#[macroquad::main(window_conf)]
-async fn main() {
-    rtiow::run(ASPECT_RATIO).await;
+fn main() {
+    let options = Options::from_args().unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        std::process::exit(1);
+    });
+
+    if let Some(address) = &options.worker {
+        if let Err(error) = rtiow::run_worker(address.as_str()) {
+            eprintln!("Worker failed: {}", error);
+            std::process::exit(1);
+        }
+    } else if options.headless {
+        if let Err(error) = rtiow::render_headless(ASPECT_RATIO, options) {
+            eprintln!("Render failed: {}", error);
+            std::process::exit(1);
+        }
+    } else {
+        run_viewer(options);
+    }
+}
+
+#[cfg(feature = "viewer")]
+fn run_viewer(options: Options) {
+    macroquad::Window::from_config(
+        window_conf(options.settings.width as i32),
+        rtiow::run(ASPECT_RATIO, options),
+    );
+}
+
+#[cfg(not(feature = "viewer"))]
+fn run_viewer(_options: Options) {
+    eprintln!("rtiow was built without the viewer feature; use --headless");
+    std::process::exit(1);
 }
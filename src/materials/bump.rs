@@ -0,0 +1,151 @@
+use std::sync::Arc;
+
+use crate::prelude::*;
+use crate::texture::{IntoTexture, Texture};
+
+/// Step used to take finite differences of a bump map.
+const BUMP_DELTA: f64 = 1e-4;
+
+/// Tilts the shading normal of another material using a tangent-space normal
+/// map, where red and green run along u and v and blue points out of the
+/// surface. Load maps with `ImageTexture::open_raw`, as they hold directions
+/// rather than colours.
+#[derive(Debug)]
+pub struct NormalMapped {
+    material: Arc<dyn Material>,
+    map: Arc<dyn Texture>,
+}
+
+impl NormalMapped {
+    pub fn new(material: Arc<dyn Material>, map: impl IntoTexture) -> Self {
+        Self {
+            material,
+            map: map.into_texture(),
+        }
+    }
+
+    fn shade(&self, rec: &Hit) -> Hit {
+        let tangent = (rec.dpdu - rec.dpdu.dot(&rec.normal) * rec.normal).unit_vector();
+        if tangent.length_squared().is_nan() {
+            return rec.clone();
+        }
+        let mut bitangent = rec.normal.cross(&tangent);
+        if bitangent.dot(&rec.dpdv) < 0. {
+            bitangent = -bitangent;
+        }
+
+        let texel = self.map.value(rec.u, rec.v, &rec.p);
+        let local = 2. * texel - Vector3::new(1, 1, 1);
+        let mut shaded = rec.clone();
+        shaded.normal =
+            (local.x() * tangent + local.y() * bitangent + local.z() * rec.normal).unit_vector();
+        shaded
+    }
+}
+
+/// Perturbs the shading normal of another material as though the surface were
+/// raised by a scalar height map, which can be an image or procedural noise.
+/// The geometry itself is not displaced, so silhouettes stay smooth.
+#[derive(Debug)]
+pub struct BumpMapped {
+    material: Arc<dyn Material>,
+    height: Arc<dyn Texture>,
+    strength: f64,
+}
+
+impl BumpMapped {
+    /// `strength` scales the height map into scene units.
+    pub fn new(material: Arc<dyn Material>, height: impl IntoTexture, strength: f64) -> Self {
+        Self {
+            material,
+            height: height.into_texture(),
+            strength,
+        }
+    }
+
+    fn shade(&self, rec: &Hit) -> Hit {
+        // Differentiate the height along u and v, moving the lookup point too
+        // so that solid textures like noise work as well as image maps.
+        let height = |u: f64, v: f64, p: &Point3| self.strength * self.height.scalar(u, v, p);
+        let base = height(rec.u, rec.v, &rec.p);
+        let dhdu = (height(rec.u + BUMP_DELTA, rec.v, &(rec.p + BUMP_DELTA * rec.dpdu)) - base)
+            / BUMP_DELTA;
+        let dhdv = (height(rec.u, rec.v + BUMP_DELTA, &(rec.p + BUMP_DELTA * rec.dpdv)) - base)
+            / BUMP_DELTA;
+
+        let dpdu = rec.dpdu + dhdu * rec.normal;
+        let dpdv = rec.dpdv + dhdv * rec.normal;
+        let normal = dpdu.cross(&dpdv).unit_vector();
+        let mut shaded = rec.clone();
+        if normal.length_squared().is_nan() {
+            return shaded;
+        }
+        shaded.normal = match normal.dot(&rec.normal) < 0. {
+            true => -normal,
+            false => normal,
+        };
+        shaded.dpdu = dpdu;
+        shaded.dpdv = dpdv;
+        shaded
+    }
+}
+
+impl Material for NormalMapped {
+    fn scatter(&self, ray: &Ray, rec: &Hit) -> ScatterResult {
+        self.material.scatter(ray, &self.shade(rec))
+    }
+
+    fn bsdf(&self, ray: &Ray, rec: &Hit, direction: &Vector3) -> Colour {
+        self.material.bsdf(ray, &self.shade(rec), direction)
+    }
+
+    fn pdf(&self, ray: &Ray, rec: &Hit, direction: &Vector3) -> f64 {
+        self.material.pdf(ray, &self.shade(rec), direction)
+    }
+
+    fn albedo(&self, rec: &Hit) -> Colour {
+        self.material.albedo(rec)
+    }
+
+    fn emitted(&self, rec: &Hit) -> Colour {
+        self.material.emitted(rec)
+    }
+
+    fn average_emission(&self) -> Colour {
+        self.material.average_emission()
+    }
+
+    fn name(&self) -> &'static str {
+        self.material.name()
+    }
+}
+
+impl Material for BumpMapped {
+    fn scatter(&self, ray: &Ray, rec: &Hit) -> ScatterResult {
+        self.material.scatter(ray, &self.shade(rec))
+    }
+
+    fn bsdf(&self, ray: &Ray, rec: &Hit, direction: &Vector3) -> Colour {
+        self.material.bsdf(ray, &self.shade(rec), direction)
+    }
+
+    fn pdf(&self, ray: &Ray, rec: &Hit, direction: &Vector3) -> f64 {
+        self.material.pdf(ray, &self.shade(rec), direction)
+    }
+
+    fn albedo(&self, rec: &Hit) -> Colour {
+        self.material.albedo(rec)
+    }
+
+    fn emitted(&self, rec: &Hit) -> Colour {
+        self.material.emitted(rec)
+    }
+
+    fn average_emission(&self) -> Colour {
+        self.material.average_emission()
+    }
+
+    fn name(&self) -> &'static str {
+        self.material.name()
+    }
+}
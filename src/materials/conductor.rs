@@ -0,0 +1,132 @@
+use rand::{thread_rng, Rng};
+
+use crate::materials::microfacet::{fresnel_conductor, reflect, Frame, Ggx};
+use crate::prelude::*;
+
+/// A metal with a GGX microfacet surface and per-channel complex index of
+/// refraction.
+#[derive(Debug)]
+pub struct Conductor {
+    eta: Colour,
+    k: Colour,
+    ggx: Ggx,
+}
+
+impl Conductor {
+    pub fn new(eta: Colour, k: Colour, roughness: f64) -> Self {
+        Self::new_anisotropic(eta, k, roughness, roughness)
+    }
+
+    /// Roughness can differ along the surface tangent and bitangent, which
+    /// stretches highlights like brushed metal.
+    pub fn new_anisotropic(eta: Colour, k: Colour, roughness_u: f64, roughness_v: f64) -> Self {
+        Self {
+            eta,
+            k,
+            ggx: Ggx::new(roughness_u, roughness_v),
+        }
+    }
+
+    pub fn gold(roughness: f64) -> Self {
+        Self::new(
+            Colour::new(0.143119, 0.374957, 1.44248),
+            Colour::new(3.98316, 2.38572, 1.60322),
+            roughness,
+        )
+    }
+
+    pub fn copper(roughness: f64) -> Self {
+        Self::new(
+            Colour::new(0.200438, 0.924033, 1.10221),
+            Colour::new(3.91295, 2.45285, 2.14219),
+            roughness,
+        )
+    }
+
+    pub fn aluminium(roughness: f64) -> Self {
+        Self::aluminium_anisotropic(roughness, roughness)
+    }
+
+    /// Aluminium with a different roughness along each tangent, as when brushed.
+    pub fn aluminium_anisotropic(roughness_u: f64, roughness_v: f64) -> Self {
+        Self::new_anisotropic(
+            Colour::new(1.65746, 0.880369, 0.521229),
+            Colour::new(9.22387, 6.26952, 4.837),
+            roughness_u,
+            roughness_v,
+        )
+    }
+
+    pub fn silver(roughness: f64) -> Self {
+        Self::new(
+            Colour::new(0.155265, 0.116723, 0.138342),
+            Colour::new(4.82835, 3.12225, 2.14696),
+            roughness,
+        )
+    }
+
+    fn fresnel(&self, cos_theta: f64) -> Colour {
+        Colour::new(
+            fresnel_conductor(cos_theta, self.eta.x(), self.k.x()),
+            fresnel_conductor(cos_theta, self.eta.y(), self.k.y()),
+            fresnel_conductor(cos_theta, self.eta.z(), self.k.z()),
+        )
+    }
+}
+
+impl Material for Conductor {
+    fn scatter(&self, ray: &Ray, rec: &Hit) -> ScatterResult {
+        let frame = Frame::from_hit(rec);
+        let wo = frame.to_local(&-ray.direction().unit_vector());
+        if wo.z() <= 0. {
+            return Absorbed {
+                attenuation: None,
+                scattered: None,
+            };
+        }
+
+        let mut rng = thread_rng();
+        let h = self.ggx.sample_visible_normal(&wo, rng.gen(), rng.gen());
+        let wi = reflect(&wo, &h);
+        if wi.z() <= 0. {
+            return Absorbed {
+                attenuation: None,
+                scattered: None,
+            };
+        }
+
+        // With visible normals sampled, the estimator reduces to F * G2 / G1.
+        let weight = self.ggx.g2(&wo, &wi) / self.ggx.g1(&wo);
+        Scattered {
+            attenuation: self.fresnel(wo.dot(&h)) * weight,
+            scattered: Ray::new(rec.p, frame.to_world(&wi)),
+            kind: BounceKind::Specular,
+            delta: false,
+        }
+    }
+
+    fn bsdf(&self, ray: &Ray, rec: &Hit, direction: &Vector3) -> Colour {
+        let frame = Frame::from_hit(rec);
+        let wo = frame.to_local(&-ray.direction().unit_vector());
+        let wi = frame.to_local(direction);
+        let h = (wo + wi).unit_vector();
+        self.ggx.reflection(&wo, &wi, &self.fresnel(wo.dot(&h))) * wi.z().max(0.)
+    }
+
+    fn pdf(&self, ray: &Ray, rec: &Hit, direction: &Vector3) -> f64 {
+        let frame = Frame::from_hit(rec);
+        let wo = frame.to_local(&-ray.direction().unit_vector());
+        match frame.to_local(direction) {
+            wi if wi.z() > 0. => self.ggx.reflection_pdf(&wo, &wi),
+            _ => 0.,
+        }
+    }
+
+    fn albedo(&self, _rec: &Hit) -> Colour {
+        self.fresnel(1.)
+    }
+
+    fn name(&self) -> &'static str {
+        "Conductor"
+    }
+}
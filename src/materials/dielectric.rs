@@ -1,18 +1,112 @@
+use crate::materials::microfacet::{reflect, Frame, Ggx};
 use crate::prelude::*;
 
 use num_traits::Pow;
 use rand::{thread_rng, Rng};
 
-#[derive(Default)]
+/// How a glass's index of refraction varies with wavelength (in nm).
+#[derive(Clone, Copy, Debug)]
+pub enum Dispersion {
+    /// n = a + b / λ², with λ in micrometres.
+    Cauchy { a: f64, b: f64 },
+    /// n² = 1 + Σ bᵢλ² / (λ² - cᵢ), with λ in micrometres.
+    Sellmeier { b: [f64; 3], c: [f64; 3] },
+}
+
+impl Dispersion {
+    pub fn index_of_refraction(&self, wavelength: f64) -> f64 {
+        let lambda2 = (wavelength / 1000.).powi(2);
+        match self {
+            Dispersion::Cauchy { a, b } => a + b / lambda2,
+            Dispersion::Sellmeier { b, c } => (1.
+                + b.iter()
+                    .zip(c.iter())
+                    .map(|(b, c)| b * lambda2 / (lambda2 - c))
+                    .sum::<f64>())
+            .sqrt(),
+        }
+    }
+}
+
+/// The sodium D line, where glass makers quote a single index of refraction.
+const REFERENCE_WAVELENGTH: f64 = 589.3;
+
+#[derive(Debug, Default)]
 pub struct Dielectric {
     index_of_refraction: f64,
+    /// Only takes effect when rendering spectrally; otherwise the index at
+    /// the reference wavelength is used.
+    dispersion: Option<Dispersion>,
+    /// Present for frosted glass; smooth glass refracts perfectly.
+    roughness: Option<Ggx>,
+    /// Beer-Lambert absorption coefficient per unit distance travelled inside.
+    absorption: Colour,
 }
 
 impl Dielectric {
     pub fn new(index_of_refraction: f64) -> Self {
         Self {
             index_of_refraction,
+            ..Default::default()
+        }
+    }
+
+    pub fn new_dispersive(dispersion: Dispersion) -> Self {
+        Self {
+            index_of_refraction: dispersion.index_of_refraction(REFERENCE_WAVELENGTH),
+            dispersion: Some(dispersion),
+            ..Default::default()
+        }
+    }
+
+    /// Borosilicate crown glass (Schott N-BK7), with little dispersion.
+    pub fn crown_glass() -> Self {
+        Self::new_dispersive(Dispersion::Sellmeier {
+            b: [1.03961212, 0.231792344, 1.01046945],
+            c: [0.00600069867, 0.0200179144, 103.560653],
+        })
+    }
+
+    /// Dense flint glass (Schott SF11), with strong dispersion.
+    pub fn flint_glass() -> Self {
+        Self::new_dispersive(Dispersion::Sellmeier {
+            b: [1.73759695, 0.313747346, 1.89878101],
+            c: [0.013188707, 0.0623068142, 155.23629],
+        })
+    }
+
+    pub fn diamond() -> Self {
+        Self::new_dispersive(Dispersion::Sellmeier {
+            b: [4.3356, 0.3306, 0.],
+            c: [0.1060 * 0.1060, 0.1750 * 0.1750, 0.],
+        })
+    }
+
+    /// Frosts the surface using a GGX distribution of microfacets.
+    pub fn with_roughness(mut self, roughness: f64) -> Self {
+        self.roughness = match roughness > 0. {
+            true => Some(Ggx::new(roughness, roughness)),
+            false => None,
+        };
+        self
+    }
+
+    /// Tints the glass so that light travelling `distance` through it is
+    /// filtered to `colour`; thicker glass gets darker, thinner stays clearer.
+    /// A distance that isn't positive leaves the glass clear.
+    pub fn with_tint(mut self, colour: Colour, distance: f64) -> Self {
+        if distance.is_nan() || distance <= 0. {
+            self.absorption = Colour::default();
+            return self;
         }
+        let absorption =
+            |transmittance: f64| (-transmittance.clamp(1e-6, 1.).ln() / distance).min(f64::MAX);
+        self.absorption = Colour::new(
+            absorption(colour.x()),
+            absorption(colour.y()),
+            absorption(colour.z()),
+        );
+        self
     }
 
     fn reflectance(cosine: f64, ref_idx: f64) -> f64 {
@@ -21,15 +115,17 @@ impl Dielectric {
         r0 *= r0;
         r0 + (1. - r0) * (1. - cosine).pow(5.)
     }
-}
 
-impl Material for Dielectric {
-    fn scatter(&self, ray: &Ray, rec: &Hit) -> ScatterResult {
-        let refraction_ratio = match rec.front_face {
-            true => 1. / self.index_of_refraction,
-            false => self.index_of_refraction,
-        };
+    /// Light leaving the glass has been absorbed along the path it took
+    /// through it.
+    fn transmittance(&self, ray: &Ray, rec: &Hit) -> Colour {
+        match rec.front_face {
+            true => Colour::new(1, 1, 1),
+            false => (-rec.t * ray.direction().length() * self.absorption).exp(),
+        }
+    }
 
+    fn scatter_smooth(&self, ray: &Ray, rec: &Hit, refraction_ratio: f64) -> Vector3 {
         let unit_direction = ray.direction().unit_vector();
 
         let cos_theta = (-1. * unit_direction).dot(&rec.normal).min(1.);
@@ -38,14 +134,99 @@ impl Material for Dielectric {
         let cannot_refract = refraction_ratio * sin_theta > 1.
             || Dielectric::reflectance(cos_theta, refraction_ratio) > thread_rng().gen::<f64>();
 
-        let direction = match cannot_refract {
+        match cannot_refract {
             true => unit_direction.reflect(&rec.normal),
             false => unit_direction.refract(&rec.normal, refraction_ratio),
+        }
+    }
+
+    /// Samples a visible microfacet, then reflects or refracts through it.
+    /// Returns the direction and the Smith masking weight, or `None` when the
+    /// sampled direction ends up on the wrong side of the surface.
+    fn scatter_rough(
+        &self,
+        ggx: &Ggx,
+        ray: &Ray,
+        rec: &Hit,
+        refraction_ratio: f64,
+    ) -> Option<(Vector3, f64)> {
+        let frame = Frame::from_hit(rec);
+        let wo = frame.to_local(&-ray.direction().unit_vector());
+        if wo.z() <= 0. {
+            return None;
+        }
+
+        let mut rng = thread_rng();
+        let h = ggx.sample_visible_normal(&wo, rng.gen(), rng.gen());
+        let cos_theta = wo.dot(&h).min(1.);
+        let sin_theta = (1. - cos_theta * cos_theta).sqrt();
+
+        let cannot_refract = refraction_ratio * sin_theta > 1.
+            || Dielectric::reflectance(cos_theta, refraction_ratio) > rng.gen::<f64>();
+
+        let wi = match cannot_refract {
+            true => reflect(&wo, &h),
+            false => (-wo).refract(&h, refraction_ratio),
+        };
+        let on_expected_side = match cannot_refract {
+            true => wi.z() > 0.,
+            false => wi.z() < 0.,
+        };
+        match on_expected_side {
+            true => Some((frame.to_world(&wi), ggx.g2(&wo, &wi) / ggx.g1(&wo))),
+            false => None,
+        }
+    }
+}
+
+impl Material for Dielectric {
+    fn scatter(&self, ray: &Ray, rec: &Hit) -> ScatterResult {
+        let (index_of_refraction, wavelengths) = match (&self.dispersion, ray.wavelengths()) {
+            (Some(dispersion), Some(wavelengths)) => (
+                dispersion.index_of_refraction(wavelengths.hero()),
+                Some(wavelengths.terminate_secondary()),
+            ),
+            _ => (self.index_of_refraction, None),
+        };
+        let refraction_ratio = match rec.front_face {
+            true => 1. / index_of_refraction,
+            false => index_of_refraction,
         };
 
+        let (direction, weight) = match &self.roughness {
+            None => (self.scatter_smooth(ray, rec, refraction_ratio), 1.),
+            Some(ggx) => match self.scatter_rough(ggx, ray, rec, refraction_ratio) {
+                Some(scattered) => scattered,
+                None => {
+                    return Absorbed {
+                        attenuation: None,
+                        scattered: None,
+                    }
+                }
+            },
+        };
+
+        let scattered = Ray::new(rec.p, direction);
+        let kind = match direction.dot(&rec.normal) < 0. {
+            true => BounceKind::Transmission,
+            false => BounceKind::Specular,
+        };
         Scattered {
-            attenuation: Colour::new(1., 1., 1.),
-            scattered: Ray::new(rec.p, direction),
+            attenuation: weight * self.transmittance(ray, rec),
+            scattered: match wavelengths {
+                Some(wavelengths) => scattered.with_wavelengths(wavelengths),
+                None => scattered,
+            },
+            kind,
+            delta: true,
         }
     }
+
+    fn albedo(&self, _rec: &Hit) -> Colour {
+        Colour::new(1, 1, 1)
+    }
+
+    fn name(&self) -> &'static str {
+        "Dielectric"
+    }
 }
@@ -1,6 +1,8 @@
+use std::f64::consts::PI;
+
 use crate::prelude::*;
 
-#[derive(Default)]
+#[derive(Debug, Default)]
 pub struct Lambertian {
     albedo: Colour,
 }
@@ -21,6 +23,24 @@ impl Material for Lambertian {
         Scattered {
             attenuation: self.albedo,
             scattered: Ray::new(rec.p, scatter_direction),
+            kind: BounceKind::Diffuse,
+            delta: false,
         }
     }
+
+    fn bsdf(&self, _ray: &Ray, rec: &Hit, direction: &Vector3) -> Colour {
+        self.albedo / PI * rec.normal.dot(direction).max(0.)
+    }
+
+    fn pdf(&self, _ray: &Ray, rec: &Hit, direction: &Vector3) -> f64 {
+        rec.normal.dot(direction).max(0.) / PI
+    }
+
+    fn albedo(&self, _rec: &Hit) -> Colour {
+        self.albedo
+    }
+
+    fn name(&self) -> &'static str {
+        "Lambertian"
+    }
 }
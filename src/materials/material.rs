@@ -1,5 +1,18 @@
+use std::fmt::Debug;
+
 use crate::prelude::*;
 
+/// What sort of bounce a scattered ray took, so that each sort can be given
+/// its own depth limit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BounceKind {
+    Diffuse,
+    /// Reflection off a smooth or glossy surface.
+    Specular,
+    /// Refraction into or out of a surface, or a step through its inside.
+    Transmission,
+}
+
 pub enum ScatterResult {
     Absorbed {
         attenuation: Option<Colour>,
@@ -8,9 +21,38 @@ pub enum ScatterResult {
     Scattered {
         attenuation: Colour,
         scattered: Ray,
+        kind: BounceKind,
+        /// Set when the direction was picked in a way `pdf` doesn't describe,
+        /// such as a perfectly smooth reflection, so that light found along it
+        /// isn't weighed against shadow rays that could never find it.
+        delta: bool,
     },
 }
 
-pub trait Material: Sync + Send {
+pub trait Material: Sync + Send + Debug {
     fn scatter(&self, ray: &Ray, rec: &Hit) -> ScatterResult;
+    /// How much of the light arriving from `direction` leaves back along
+    /// `ray`, including the cosine term. Used for lights that can only be
+    /// reached by shadow rays; perfectly smooth surfaces never see those, so
+    /// the default is black.
+    fn bsdf(&self, _ray: &Ray, _rec: &Hit, _direction: &Vector3) -> Colour {
+        Colour::default()
+    }
+    /// The pdf per unit solid angle of `scatter` choosing `direction`, used to
+    /// weigh scattered rays against shadow rays. Materials that override
+    /// `bsdf` must override this too; zero means a perfectly smooth surface.
+    fn pdf(&self, _ray: &Ray, _rec: &Hit, _direction: &Vector3) -> f64 {
+        0.
+    }
+    fn albedo(&self, rec: &Hit) -> Colour;
+    /// Light given off by the surface, which is none for most materials.
+    fn emitted(&self, _rec: &Hit) -> Colour {
+        Colour::default()
+    }
+    /// A rough figure for the light given off anywhere on the surface, used
+    /// to find emitters and decide how often to aim shadow rays at them.
+    fn average_emission(&self) -> Colour {
+        Colour::default()
+    }
+    fn name(&self) -> &'static str;
 }
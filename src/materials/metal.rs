@@ -1,6 +1,6 @@
 use crate::prelude::*;
 
-#[derive(Default)]
+#[derive(Debug, Default)]
 pub struct Metal {
     albedo: Colour,
     blur: f64,
@@ -31,6 +31,8 @@ impl Material for Metal {
                     rec.p,
                     reflection_direction + (Vector3::random_in_unit_sphere() * self.blur),
                 ),
+                kind: BounceKind::Specular,
+                delta: true,
             },
             false => Absorbed {
                 attenuation: None,
@@ -38,4 +40,12 @@ impl Material for Metal {
             },
         }
     }
+
+    fn albedo(&self, _rec: &Hit) -> Colour {
+        self.albedo
+    }
+
+    fn name(&self) -> &'static str {
+        "Metal"
+    }
 }
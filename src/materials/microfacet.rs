@@ -0,0 +1,204 @@
+use std::f64::consts::PI;
+
+use crate::prelude::*;
+
+/// Below this, roughness is clamped so the GGX maths stays finite.
+const MIN_ALPHA: f64 = 1e-4;
+
+/// An orthonormal basis with the surface normal as its z axis.
+pub struct Frame {
+    tangent: Vector3,
+    bitangent: Vector3,
+    normal: Vector3,
+}
+
+impl Frame {
+    /// Aligns the tangent with the surface's u direction, so anisotropic
+    /// highlights follow the surface parameterisation.
+    pub fn from_hit(rec: &Hit) -> Self {
+        let tangent = rec.dpdu - rec.dpdu.dot(&rec.normal) * rec.normal;
+        match tangent.length_squared() > 1e-12 {
+            true => {
+                let tangent = tangent.unit_vector();
+                Self {
+                    tangent,
+                    bitangent: rec.normal.cross(&tangent),
+                    normal: rec.normal,
+                }
+            }
+            false => Self::from_normal(&rec.normal),
+        }
+    }
+
+    /// Builds the tangents around the world y axis, for surfaces without a
+    /// parameterisation.
+    pub fn from_normal(normal: &Vector3) -> Self {
+        let reference = match normal.y().abs() < 0.999 {
+            true => Vector3::new(0, 1, 0),
+            false => Vector3::new(1, 0, 0),
+        };
+        let tangent = reference.cross(normal).unit_vector();
+        Self {
+            tangent,
+            bitangent: normal.cross(&tangent),
+            normal: *normal,
+        }
+    }
+
+    pub fn to_local(&self, v: &Vector3) -> Vector3 {
+        Vector3::new(
+            v.dot(&self.tangent),
+            v.dot(&self.bitangent),
+            v.dot(&self.normal),
+        )
+    }
+
+    pub fn to_world(&self, v: &Vector3) -> Vector3 {
+        v.x() * self.tangent + v.y() * self.bitangent + v.z() * self.normal
+    }
+}
+
+/// The GGX (Trowbridge-Reitz) distribution of microfacet normals, working in
+/// a local frame where the macro surface normal is +z.
+#[derive(Clone, Copy, Debug)]
+pub struct Ggx {
+    alpha_x: f64,
+    alpha_y: f64,
+}
+
+impl Ggx {
+    /// Takes perceptual roughness in [0, 1] along each tangent, squaring it to
+    /// get the distribution's alpha.
+    pub fn new(roughness_x: f64, roughness_y: f64) -> Self {
+        let alpha =
+            |roughness: f64| (roughness.clamp(0., 1.) * roughness.clamp(0., 1.)).max(MIN_ALPHA);
+        Self {
+            alpha_x: alpha(roughness_x),
+            alpha_y: alpha(roughness_y),
+        }
+    }
+
+    pub fn distribution(&self, h: &Vector3) -> f64 {
+        if h.z() <= 0. {
+            return 0.;
+        }
+        let x = h.x() / self.alpha_x;
+        let y = h.y() / self.alpha_y;
+        let d = x * x + y * y + h.z() * h.z();
+        1. / (PI * self.alpha_x * self.alpha_y * d * d)
+    }
+
+    fn lambda(&self, w: &Vector3) -> f64 {
+        let z2 = w.z() * w.z();
+        if z2 == 0. {
+            return f64::INFINITY;
+        }
+        let a2 = (self.alpha_x * w.x()).powi(2) + (self.alpha_y * w.y()).powi(2);
+        ((1. + a2 / z2).sqrt() - 1.) / 2.
+    }
+
+    /// Smith masking for a single direction.
+    pub fn g1(&self, w: &Vector3) -> f64 {
+        1. / (1. + self.lambda(w))
+    }
+
+    /// Smith height-correlated masking-shadowing.
+    pub fn g2(&self, wo: &Vector3, wi: &Vector3) -> f64 {
+        1. / (1. + self.lambda(wo) + self.lambda(wi))
+    }
+
+    /// The pdf of reflecting `wo` into `wi` when the half vector was drawn
+    /// by `sample_visible_normal`.
+    pub fn reflection_pdf(&self, wo: &Vector3, wi: &Vector3) -> f64 {
+        let h = (wo + wi).unit_vector();
+        match wo.z() > 0. && wo.dot(&h) > 0. {
+            true => self.g1(wo) * self.distribution(&h) / (4. * wo.z()),
+            false => 0.,
+        }
+    }
+
+    /// Evaluates the microfacet reflection for a given Fresnel term, without
+    /// the cosine factor.
+    pub fn reflection(&self, wo: &Vector3, wi: &Vector3, fresnel: &Colour) -> Colour {
+        if wo.z() <= 0. || wi.z() <= 0. {
+            return Colour::default();
+        }
+        let h = (wo + wi).unit_vector();
+        self.distribution(&h) * self.g2(wo, wi) / (4. * wo.z() * wi.z()) * fresnel
+    }
+
+    /// Samples a microfacet normal from the distribution of normals visible
+    /// from `wo` (Heitz 2018), given two uniform random numbers.
+    pub fn sample_visible_normal(&self, wo: &Vector3, u1: f64, u2: f64) -> Vector3 {
+        let vh = Vector3::new(self.alpha_x * wo.x(), self.alpha_y * wo.y(), wo.z()).unit_vector();
+
+        let length_squared = vh.x() * vh.x() + vh.y() * vh.y();
+        let t1 = match length_squared > 0. {
+            true => Vector3::new(-vh.y(), vh.x(), 0) / length_squared.sqrt(),
+            false => Vector3::new(1, 0, 0),
+        };
+        let t2 = vh.cross(&t1);
+
+        let r = u1.sqrt();
+        let phi = 2. * PI * u2;
+        let p1 = r * phi.cos();
+        let s = 0.5 * (1. + vh.z());
+        let p2 = (1. - s) * (1. - p1 * p1).sqrt() + s * r * phi.sin();
+        let nh = p1 * t1 + p2 * t2 + (1. - p1 * p1 - p2 * p2).max(0.).sqrt() * vh;
+
+        Vector3::new(
+            self.alpha_x * nh.x(),
+            self.alpha_y * nh.y(),
+            nh.z().max(1e-6),
+        )
+        .unit_vector()
+    }
+}
+
+/// Cosine-weighted direction in the local frame, with a pdf of cos θ / π.
+pub fn sample_cosine_hemisphere(u1: f64, u2: f64) -> Vector3 {
+    let r = u1.sqrt();
+    let phi = 2. * PI * u2;
+    Vector3::new(r * phi.cos(), r * phi.sin(), (1. - u1).max(0.).sqrt())
+}
+
+/// Reflects `wo` about the microfacet normal `h`; both point away from the surface.
+pub fn reflect(wo: &Vector3, h: &Vector3) -> Vector3 {
+    2. * wo.dot(h) * h - wo
+}
+
+/// Fresnel reflectance of a dielectric interface, where `eta` is the index of
+/// refraction on the far side over the index on the near side.
+pub fn fresnel_dielectric(cos_theta_i: f64, eta: f64) -> f64 {
+    let cos_i = cos_theta_i.clamp(0., 1.);
+    let sin2_t = (1. - cos_i * cos_i) / (eta * eta);
+    if sin2_t >= 1. {
+        return 1.;
+    }
+    let cos_t = (1. - sin2_t).sqrt();
+    let rs = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    let rp = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    0.5 * (rs * rs + rp * rp)
+}
+
+/// Fresnel reflectance of a conductor with complex index of refraction
+/// `eta + ik`, for unpolarised light arriving from air.
+pub fn fresnel_conductor(cos_theta_i: f64, eta: f64, k: f64) -> f64 {
+    let cos2 = cos_theta_i.clamp(0., 1.).powi(2);
+    let sin2 = 1. - cos2;
+    let eta2 = eta * eta;
+    let k2 = k * k;
+
+    let t0 = eta2 - k2 - sin2;
+    let a2_plus_b2 = (t0 * t0 + 4. * eta2 * k2).sqrt();
+    let t1 = a2_plus_b2 + cos2;
+    let a = (0.5 * (a2_plus_b2 + t0)).max(0.).sqrt();
+    let t2 = 2. * cos_theta_i.clamp(0., 1.) * a;
+    let rs = (t1 - t2) / (t1 + t2);
+
+    let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+    let t4 = t2 * sin2;
+    let rp = rs * (t3 - t4) / (t3 + t4);
+
+    0.5 * (rp + rs)
+}
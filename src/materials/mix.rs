@@ -0,0 +1,78 @@
+use std::sync::Arc;
+
+use rand::{thread_rng, Rng};
+
+use crate::prelude::*;
+use crate::texture::{IntoTexture, Texture};
+
+/// Blends two materials, picking one at random at each hit. A weight of 0 is
+/// all `first` and 1 is all `second`. The picked component's `delta` flag
+/// passes through, so light found along a smooth component's reflection
+/// isn't weighed against the blended `pdf`.
+#[derive(Debug)]
+pub struct MixMaterial {
+    first: Arc<dyn Material>,
+    second: Arc<dyn Material>,
+    weight: Arc<dyn Texture>,
+}
+
+impl MixMaterial {
+    pub fn new(
+        first: Arc<dyn Material>,
+        second: Arc<dyn Material>,
+        weight: impl IntoTexture,
+    ) -> Self {
+        Self {
+            first,
+            second,
+            weight: weight.into_texture(),
+        }
+    }
+
+    fn weight(&self, rec: &Hit) -> f64 {
+        self.weight.scalar(rec.u, rec.v, &rec.p).clamp(0., 1.)
+    }
+}
+
+impl Material for MixMaterial {
+    fn scatter(&self, ray: &Ray, rec: &Hit) -> ScatterResult {
+        match thread_rng().gen::<f64>() < self.weight(rec) {
+            true => self.second.scatter(ray, rec),
+            false => self.first.scatter(ray, rec),
+        }
+    }
+
+    fn bsdf(&self, ray: &Ray, rec: &Hit, direction: &Vector3) -> Colour {
+        let weight = self.weight(rec);
+        (1. - weight) * self.first.bsdf(ray, rec, direction)
+            + weight * self.second.bsdf(ray, rec, direction)
+    }
+
+    fn pdf(&self, ray: &Ray, rec: &Hit, direction: &Vector3) -> f64 {
+        let weight = self.weight(rec);
+        (1. - weight) * self.first.pdf(ray, rec, direction)
+            + weight * self.second.pdf(ray, rec, direction)
+    }
+
+    fn albedo(&self, rec: &Hit) -> Colour {
+        let weight = self.weight(rec);
+        (1. - weight) * self.first.albedo(rec) + weight * self.second.albedo(rec)
+    }
+
+    fn emitted(&self, rec: &Hit) -> Colour {
+        let weight = self.weight(rec);
+        (1. - weight) * self.first.emitted(rec) + weight * self.second.emitted(rec)
+    }
+
+    fn average_emission(&self) -> Colour {
+        let weight = self
+            .weight
+            .scalar(0.5, 0.5, &Point3::default())
+            .clamp(0., 1.);
+        (1. - weight) * self.first.average_emission() + weight * self.second.average_emission()
+    }
+
+    fn name(&self) -> &'static str {
+        "Mix"
+    }
+}
@@ -1,4 +1,11 @@
+pub mod bump;
+pub mod conductor;
 pub mod dielectric;
 pub mod lambertian;
 pub mod material;
 pub mod metal;
+pub mod microfacet;
+pub mod mix;
+pub mod plastic;
+pub mod principled;
+pub mod subsurface;
@@ -0,0 +1,154 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+use rand::{thread_rng, Rng};
+
+use crate::materials::microfacet::{
+    fresnel_dielectric, reflect, sample_cosine_hemisphere, Frame, Ggx,
+};
+use crate::prelude::*;
+use crate::texture::{IntoTexture, Texture};
+
+/// A diffuse base under a clear dielectric coat, like glossy plastic or
+/// varnished wood.
+///
+/// Light the coat lets through bounces around under it, partly reflected back
+/// down by the coat each time, so the base looks darker and more saturated
+/// than it would bare, and no energy is created or lost.
+#[derive(Debug)]
+pub struct Plastic {
+    diffuse: Arc<dyn Texture>,
+    coat: Ggx,
+    index_of_refraction: f64,
+    /// Fraction of diffuse light under the coat that it reflects back down.
+    internal_reflectance: f64,
+}
+
+impl Plastic {
+    pub fn new(diffuse: impl IntoTexture) -> Self {
+        Self::coated(diffuse, 0., 1.5)
+    }
+
+    /// A coat with the given roughness and index of refraction over a
+    /// diffuse base.
+    pub fn coated(diffuse: impl IntoTexture, roughness: f64, index_of_refraction: f64) -> Self {
+        // Average the coat's reflectance over a cosine-weighted hemisphere,
+        // then account for total internal reflection from the inside.
+        let steps = 1000;
+        let external_reflectance: f64 = (0..steps)
+            .map(|step| {
+                let cos = (step as f64 + 0.5) / steps as f64;
+                2. * cos * fresnel_dielectric(cos, index_of_refraction) / steps as f64
+            })
+            .sum();
+        let internal_reflectance =
+            1. - (1. - external_reflectance) / (index_of_refraction * index_of_refraction);
+
+        Self {
+            diffuse: diffuse.into_texture(),
+            coat: Ggx::new(roughness, roughness),
+            index_of_refraction,
+            internal_reflectance,
+        }
+    }
+
+    /// Probability of sampling the coat rather than the base.
+    fn coat_probability(&self, wo: &Vector3) -> f64 {
+        fresnel_dielectric(wo.z(), self.index_of_refraction).max(0.05)
+    }
+
+    fn eval(&self, diffuse: &Colour, wo: &Vector3, wi: &Vector3) -> Colour {
+        if wo.z() <= 0. || wi.z() <= 0. {
+            return Colour::default();
+        }
+        let h = (wo + wi).unit_vector();
+        let coat_fresnel = fresnel_dielectric(wo.dot(&h), self.index_of_refraction);
+        let coat = self.coat.reflection(
+            wo,
+            wi,
+            &Colour::new(coat_fresnel, coat_fresnel, coat_fresnel),
+        );
+
+        let eta2 = self.index_of_refraction * self.index_of_refraction;
+        let transmitted = (1. - fresnel_dielectric(wo.z(), self.index_of_refraction))
+            * (1. - fresnel_dielectric(wi.z(), self.index_of_refraction));
+        let scattered_under_coat = Colour::new(
+            diffuse.x() / (1. - diffuse.x() * self.internal_reflectance),
+            diffuse.y() / (1. - diffuse.y() * self.internal_reflectance),
+            diffuse.z() / (1. - diffuse.z() * self.internal_reflectance),
+        );
+        let base = transmitted / (PI * eta2) * scattered_under_coat;
+
+        (coat + base) * wi.z()
+    }
+
+    fn local_pdf(&self, wo: &Vector3, wi: &Vector3) -> f64 {
+        if wi.z() <= 0. {
+            return 0.;
+        }
+        let coat_probability = self.coat_probability(wo);
+        coat_probability * self.coat.reflection_pdf(wo, wi) + (1. - coat_probability) * wi.z() / PI
+    }
+}
+
+impl Material for Plastic {
+    fn scatter(&self, ray: &Ray, rec: &Hit) -> ScatterResult {
+        let absorbed = Absorbed {
+            attenuation: None,
+            scattered: None,
+        };
+        let frame = Frame::from_hit(rec);
+        let wo = frame.to_local(&-ray.direction().unit_vector());
+        if wo.z() <= 0. {
+            return absorbed;
+        }
+
+        let mut rng = thread_rng();
+        let (wi, kind) = match rng.gen::<f64>() < self.coat_probability(&wo) {
+            true => (
+                reflect(
+                    &wo,
+                    &self.coat.sample_visible_normal(&wo, rng.gen(), rng.gen()),
+                ),
+                BounceKind::Specular,
+            ),
+            false => (
+                sample_cosine_hemisphere(rng.gen(), rng.gen()),
+                BounceKind::Diffuse,
+            ),
+        };
+        let pdf = self.local_pdf(&wo, &wi);
+        if wi.z() <= 0. || pdf <= 0. {
+            return absorbed;
+        }
+
+        let diffuse = self.diffuse.value(rec.u, rec.v, &rec.p);
+        Scattered {
+            attenuation: self.eval(&diffuse, &wo, &wi) / pdf,
+            scattered: Ray::new(rec.p, frame.to_world(&wi)),
+            kind,
+            delta: false,
+        }
+    }
+
+    fn bsdf(&self, ray: &Ray, rec: &Hit, direction: &Vector3) -> Colour {
+        let frame = Frame::from_hit(rec);
+        let wo = frame.to_local(&-ray.direction().unit_vector());
+        let diffuse = self.diffuse.value(rec.u, rec.v, &rec.p);
+        self.eval(&diffuse, &wo, &frame.to_local(direction))
+    }
+
+    fn pdf(&self, ray: &Ray, rec: &Hit, direction: &Vector3) -> f64 {
+        let frame = Frame::from_hit(rec);
+        let wo = frame.to_local(&-ray.direction().unit_vector());
+        self.local_pdf(&wo, &frame.to_local(direction))
+    }
+
+    fn albedo(&self, rec: &Hit) -> Colour {
+        self.diffuse.value(rec.u, rec.v, &rec.p)
+    }
+
+    fn name(&self) -> &'static str {
+        "Plastic"
+    }
+}
@@ -0,0 +1,370 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+use rand::{thread_rng, Rng};
+
+use crate::materials::microfacet::{reflect, sample_cosine_hemisphere, Frame, Ggx};
+use crate::prelude::*;
+use crate::texture::{IntoTexture, Texture};
+
+/// Parameters looked up from the textures at one point on the surface.
+struct Inputs {
+    base_colour: Colour,
+    metallic: f64,
+    roughness: f64,
+    specular: f64,
+    clearcoat: f64,
+    clearcoat_roughness: f64,
+    sheen: f64,
+    transmission: f64,
+}
+
+/// A lobe of the BSDF, picked with a probability proportional to roughly how
+/// much light it reflects.
+#[derive(Clone, Copy)]
+enum Lobe {
+    Diffuse,
+    Specular,
+    Clearcoat,
+    Transmission,
+}
+
+/// One material covering plastics, metals, paints, fabrics and glass, after
+/// Burley's "Physically Based Shading at Disney" (2012). Every parameter can
+/// be a texture.
+#[derive(Debug)]
+pub struct Principled {
+    base_colour: Arc<dyn Texture>,
+    metallic: Arc<dyn Texture>,
+    roughness: Arc<dyn Texture>,
+    specular: Arc<dyn Texture>,
+    clearcoat: Arc<dyn Texture>,
+    clearcoat_roughness: Arc<dyn Texture>,
+    sheen: Arc<dyn Texture>,
+    transmission: Arc<dyn Texture>,
+    emission: Arc<dyn Texture>,
+    index_of_refraction: f64,
+}
+
+impl Principled {
+    /// A rough dielectric of the given colour; the other parameters default to
+    /// zero apart from roughness (0.5) and specular (0.5, 4% reflectance).
+    pub fn new(base_colour: impl IntoTexture) -> Self {
+        Self {
+            base_colour: base_colour.into_texture(),
+            metallic: 0.0.into_texture(),
+            roughness: 0.5.into_texture(),
+            specular: 0.5.into_texture(),
+            clearcoat: 0.0.into_texture(),
+            clearcoat_roughness: 0.1.into_texture(),
+            sheen: 0.0.into_texture(),
+            transmission: 0.0.into_texture(),
+            emission: Colour::default().into_texture(),
+            index_of_refraction: 1.5,
+        }
+    }
+
+    pub fn metallic(mut self, metallic: impl IntoTexture) -> Self {
+        self.metallic = metallic.into_texture();
+        self
+    }
+
+    pub fn roughness(mut self, roughness: impl IntoTexture) -> Self {
+        self.roughness = roughness.into_texture();
+        self
+    }
+
+    pub fn specular(mut self, specular: impl IntoTexture) -> Self {
+        self.specular = specular.into_texture();
+        self
+    }
+
+    pub fn clearcoat(mut self, clearcoat: impl IntoTexture) -> Self {
+        self.clearcoat = clearcoat.into_texture();
+        self
+    }
+
+    pub fn clearcoat_roughness(mut self, clearcoat_roughness: impl IntoTexture) -> Self {
+        self.clearcoat_roughness = clearcoat_roughness.into_texture();
+        self
+    }
+
+    pub fn sheen(mut self, sheen: impl IntoTexture) -> Self {
+        self.sheen = sheen.into_texture();
+        self
+    }
+
+    pub fn transmission(mut self, transmission: impl IntoTexture) -> Self {
+        self.transmission = transmission.into_texture();
+        self
+    }
+
+    pub fn emission(mut self, emission: impl IntoTexture) -> Self {
+        self.emission = emission.into_texture();
+        self
+    }
+
+    pub fn index_of_refraction(mut self, index_of_refraction: f64) -> Self {
+        self.index_of_refraction = index_of_refraction;
+        self
+    }
+
+    fn inputs(&self, rec: &Hit) -> Inputs {
+        let scalar =
+            |texture: &Arc<dyn Texture>| texture.scalar(rec.u, rec.v, &rec.p).clamp(0., 1.);
+        Inputs {
+            base_colour: self.base_colour.value(rec.u, rec.v, &rec.p),
+            metallic: scalar(&self.metallic),
+            roughness: scalar(&self.roughness),
+            specular: scalar(&self.specular),
+            clearcoat: scalar(&self.clearcoat),
+            clearcoat_roughness: scalar(&self.clearcoat_roughness),
+            sheen: scalar(&self.sheen),
+            transmission: scalar(&self.transmission),
+        }
+    }
+
+    /// Probabilities of sampling each lobe, in the order of `Lobe`.
+    fn lobe_weights(inputs: &Inputs, front_face: bool) -> [f64; 4] {
+        let dielectric = 1. - inputs.metallic;
+        if !front_face && inputs.transmission > 0. {
+            // Inside a transmissive object only refraction makes sense.
+            return [0., 0., 0., 1.];
+        }
+        let weights = [
+            dielectric * (1. - inputs.transmission) * inputs.base_colour.luminance().max(0.01),
+            Self::specular_f0(inputs).luminance().max(0.04),
+            0.25 * inputs.clearcoat,
+            dielectric * inputs.transmission,
+        ];
+        let total: f64 = weights.iter().sum();
+        weights.map(|weight| weight / total)
+    }
+
+    fn specular_f0(inputs: &Inputs) -> Colour {
+        let dielectric = 0.08 * inputs.specular;
+        (1. - inputs.metallic) * Colour::new(dielectric, dielectric, dielectric)
+            + inputs.metallic * inputs.base_colour
+    }
+
+    fn schlick(f0: &Colour, cos_theta: f64) -> Colour {
+        let weight = (1. - cos_theta.clamp(0., 1.)).powi(5);
+        f0 + (Colour::new(1, 1, 1) - f0) * weight
+    }
+
+    fn specular_lobe(inputs: &Inputs) -> Ggx {
+        Ggx::new(inputs.roughness, inputs.roughness)
+    }
+
+    fn clearcoat_lobe(inputs: &Inputs) -> Ggx {
+        Ggx::new(inputs.clearcoat_roughness, inputs.clearcoat_roughness)
+    }
+
+    /// The reflective part of the BSDF times the cosine term, for directions
+    /// in the local frame.
+    fn eval(inputs: &Inputs, wo: &Vector3, wi: &Vector3) -> Colour {
+        if wo.z() <= 0. || wi.z() <= 0. {
+            return Colour::default();
+        }
+        let h = (wo + wi).unit_vector();
+        let cos_d = wi.dot(&h);
+
+        // Burley's diffuse, with retro-reflection at grazing angles for rough
+        // surfaces, plus sheen towards the silhouette.
+        let fd90 = 0.5 + 2. * inputs.roughness * cos_d * cos_d;
+        let fresnel_weight = |cos: f64| 1. + (fd90 - 1.) * (1. - cos).powi(5);
+        let diffuse_weight = (1. - inputs.metallic) * (1. - inputs.transmission);
+        let diffuse = diffuse_weight / PI
+            * fresnel_weight(wi.z())
+            * fresnel_weight(wo.z())
+            * inputs.base_colour;
+        let sheen_weight = (1. - inputs.metallic) * inputs.sheen * (1. - cos_d).powi(5);
+        let sheen = Colour::new(sheen_weight, sheen_weight, sheen_weight);
+
+        let specular = Self::specular_lobe(inputs).reflection(
+            wo,
+            wi,
+            &Self::schlick(&Self::specular_f0(inputs), wo.dot(&h)),
+        );
+        let clearcoat = 0.25
+            * inputs.clearcoat
+            * Self::clearcoat_lobe(inputs).reflection(
+                wo,
+                wi,
+                &Self::schlick(&Colour::new(0.04, 0.04, 0.04), wo.dot(&h)),
+            );
+
+        (diffuse + sheen + specular + clearcoat) * wi.z()
+    }
+
+    /// The pdf of sampling `wi` through any of the reflective lobes.
+    fn local_pdf(inputs: &Inputs, weights: &[f64; 4], wo: &Vector3, wi: &Vector3) -> f64 {
+        if wi.z() <= 0. {
+            return 0.;
+        }
+        weights[Lobe::Diffuse as usize] * wi.z() / PI
+            + weights[Lobe::Specular as usize] * Self::specular_lobe(inputs).reflection_pdf(wo, wi)
+            + weights[Lobe::Clearcoat as usize]
+                * Self::clearcoat_lobe(inputs).reflection_pdf(wo, wi)
+    }
+
+    /// Refracts through a rough interface tinted by the base colour, returning
+    /// the direction and its weight. From outside, the specular lobe already
+    /// reflects what the interface reflects, so this only carries the refracted
+    /// share; inside there is no other lobe, so Fresnel and total internal
+    /// reflection happen here.
+    fn transmit(
+        &self,
+        inputs: &Inputs,
+        wo: &Vector3,
+        front_face: bool,
+    ) -> Option<(Vector3, Colour)> {
+        let refraction_ratio = match front_face {
+            true => 1. / self.index_of_refraction,
+            false => self.index_of_refraction,
+        };
+        let ggx = Self::specular_lobe(inputs);
+        let mut rng = thread_rng();
+        let h = ggx.sample_visible_normal(wo, rng.gen(), rng.gen());
+        let cos_theta = wo.dot(&h).min(1.);
+        let sin_theta = (1. - cos_theta * cos_theta).sqrt();
+
+        let mut r0 = (1. - refraction_ratio) / (1. + refraction_ratio);
+        r0 *= r0;
+        let reflectance = r0 + (1. - r0) * (1. - cos_theta).powi(5);
+        let total_internal_reflection = refraction_ratio * sin_theta > 1.;
+        if front_face && total_internal_reflection {
+            return None;
+        }
+        let cannot_refract =
+            !front_face && (total_internal_reflection || reflectance > rng.gen::<f64>());
+
+        let (wi, tint) = match (cannot_refract, front_face) {
+            (true, _) => (reflect(wo, &h), Colour::new(1, 1, 1)),
+            (false, true) => (
+                (-*wo).refract(&h, refraction_ratio),
+                (1. - reflectance) * inputs.base_colour,
+            ),
+            (false, false) => ((-*wo).refract(&h, refraction_ratio), inputs.base_colour),
+        };
+        let on_expected_side = match cannot_refract {
+            true => wi.z() > 0.,
+            false => wi.z() < 0.,
+        };
+        match on_expected_side {
+            true => Some((wi, ggx.g2(wo, &wi) / ggx.g1(wo) * tint)),
+            false => None,
+        }
+    }
+}
+
+impl Material for Principled {
+    fn scatter(&self, ray: &Ray, rec: &Hit) -> ScatterResult {
+        let absorbed = Absorbed {
+            attenuation: None,
+            scattered: None,
+        };
+        let inputs = self.inputs(rec);
+        let frame = Frame::from_hit(rec);
+        let wo = frame.to_local(&-ray.direction().unit_vector());
+        if wo.z() <= 0. {
+            return absorbed;
+        }
+
+        let weights = Self::lobe_weights(&inputs, rec.front_face);
+        let mut rng = thread_rng();
+        let choice = rng.gen::<f64>();
+        let lobe = if choice < weights[0] {
+            Lobe::Diffuse
+        } else if choice < weights[0] + weights[1] {
+            Lobe::Specular
+        } else if choice < weights[0] + weights[1] + weights[2] {
+            Lobe::Clearcoat
+        } else {
+            Lobe::Transmission
+        };
+
+        let (wi, attenuation) = match lobe {
+            Lobe::Transmission => match self.transmit(&inputs, &wo, rec.front_face) {
+                Some((wi, weight)) => (wi, weight / weights[Lobe::Transmission as usize]),
+                None => return absorbed,
+            },
+            _ => {
+                let wi = match lobe {
+                    Lobe::Diffuse => sample_cosine_hemisphere(rng.gen(), rng.gen()),
+                    Lobe::Specular => {
+                        let h = Self::specular_lobe(&inputs).sample_visible_normal(
+                            &wo,
+                            rng.gen(),
+                            rng.gen(),
+                        );
+                        reflect(&wo, &h)
+                    }
+                    _ => {
+                        let h = Self::clearcoat_lobe(&inputs).sample_visible_normal(
+                            &wo,
+                            rng.gen(),
+                            rng.gen(),
+                        );
+                        reflect(&wo, &h)
+                    }
+                };
+                // Weighting by the pdf of all the reflective lobes together
+                // keeps the estimate low variance whichever lobe was picked.
+                let pdf = Self::local_pdf(&inputs, &weights, &wo, &wi);
+                if wi.z() <= 0. || pdf <= 0. {
+                    return absorbed;
+                }
+                (wi, Self::eval(&inputs, &wo, &wi) / pdf)
+            }
+        };
+
+        Scattered {
+            attenuation,
+            scattered: Ray::new(rec.p, frame.to_world(&wi)),
+            kind: match lobe {
+                Lobe::Diffuse => BounceKind::Diffuse,
+                Lobe::Specular | Lobe::Clearcoat => BounceKind::Specular,
+                Lobe::Transmission => BounceKind::Transmission,
+            },
+            delta: false,
+        }
+    }
+
+    fn bsdf(&self, ray: &Ray, rec: &Hit, direction: &Vector3) -> Colour {
+        let inputs = self.inputs(rec);
+        if !rec.front_face && inputs.transmission > 0. {
+            return Colour::default();
+        }
+        let frame = Frame::from_hit(rec);
+        let wo = frame.to_local(&-ray.direction().unit_vector());
+        Self::eval(&inputs, &wo, &frame.to_local(direction))
+    }
+
+    fn pdf(&self, ray: &Ray, rec: &Hit, direction: &Vector3) -> f64 {
+        let inputs = self.inputs(rec);
+        let weights = Self::lobe_weights(&inputs, rec.front_face);
+        let frame = Frame::from_hit(rec);
+        let wo = frame.to_local(&-ray.direction().unit_vector());
+        Self::local_pdf(&inputs, &weights, &wo, &frame.to_local(direction))
+    }
+
+    fn albedo(&self, rec: &Hit) -> Colour {
+        self.base_colour.value(rec.u, rec.v, &rec.p)
+    }
+
+    fn emitted(&self, rec: &Hit) -> Colour {
+        match rec.front_face {
+            true => self.emission.value(rec.u, rec.v, &rec.p),
+            false => Colour::default(),
+        }
+    }
+
+    fn average_emission(&self) -> Colour {
+        self.emission.value(0.5, 0.5, &Point3::default())
+    }
+
+    fn name(&self) -> &'static str {
+        "Principled"
+    }
+}
@@ -0,0 +1,137 @@
+use crate::prelude::*;
+
+use rand::{thread_rng, Rng};
+
+/// A translucent material that scatters light inside its volume, so light
+/// bleeds through thin parts and softens shadows, as in skin, wax and marble.
+///
+/// Light enters and leaves through a `Dielectric` boundary. Inside, each path
+/// takes a random walk: the distance to the next scattering event is drawn
+/// from the mean free path, and if it falls short of the boundary the path
+/// turns in a random direction there. Every event uses one bounce of the ray
+/// depth, so materials with a short mean free path need a higher `--depth`.
+/// The object must be closed for the walk to find its way out.
+#[derive(Debug)]
+pub struct Subsurface {
+    boundary: Dielectric,
+    /// The fraction of light kept at each scattering event, per channel.
+    albedo: Colour,
+    /// The average distance travelled between events, per channel.
+    mean_free_path: Colour,
+}
+
+impl Subsurface {
+    pub fn new(albedo: Colour, mean_free_path: Colour, index_of_refraction: f64) -> Self {
+        Self {
+            boundary: Dielectric::new(index_of_refraction),
+            albedo,
+            mean_free_path,
+        }
+    }
+
+    /// The presets suit objects about a unit across; scale the mean free
+    /// path with the object for larger or smaller ones.
+    pub fn marble() -> Self {
+        Self::new(
+            Colour::new(0.98, 0.97, 0.95),
+            Colour::new(0.25, 0.24, 0.22),
+            1.49,
+        )
+    }
+
+    /// Red light travels furthest through skin, giving it a warm glow.
+    pub fn skin() -> Self {
+        Self::new(
+            Colour::new(0.98, 0.9, 0.84),
+            Colour::new(0.3, 0.18, 0.12),
+            1.4,
+        )
+        .with_roughness(0.35)
+    }
+
+    pub fn wax() -> Self {
+        Self::new(
+            Colour::new(0.96, 0.88, 0.65),
+            Colour::new(0.3, 0.25, 0.18),
+            1.45,
+        )
+    }
+
+    /// Roughens the boundary, as for `Dielectric::with_roughness`.
+    pub fn with_roughness(mut self, roughness: f64) -> Self {
+        self.boundary = self.boundary.with_roughness(roughness);
+        self
+    }
+
+    fn extinction(&self) -> Colour {
+        Colour::new(
+            1. / self.mean_free_path.x(),
+            1. / self.mean_free_path.y(),
+            1. / self.mean_free_path.z(),
+        )
+    }
+}
+
+/// The mean of the channels of a colour.
+fn average(colour: &Colour) -> f64 {
+    (colour.x() + colour.y() + colour.z()) / 3.
+}
+
+impl Material for Subsurface {
+    fn scatter(&self, ray: &Ray, rec: &Hit) -> ScatterResult {
+        if rec.front_face {
+            return self.boundary.scatter(ray, rec);
+        }
+
+        // The ray has travelled through the volume to reach the boundary from
+        // inside. Sample a distance using the extinction of a random channel,
+        // and weight by the pdf averaged over all three.
+        let mut rng = thread_rng();
+        let extinction = self.extinction();
+        let channel_extinction = match rng.gen_range(0..3) {
+            0 => extinction.x(),
+            1 => extinction.y(),
+            _ => extinction.z(),
+        };
+        let distance = -(1. - rng.gen::<f64>()).ln() / channel_extinction;
+        let boundary_distance = rec.t * ray.direction().length();
+
+        if distance < boundary_distance {
+            let transmittance = (-distance * extinction).exp();
+            let pdf = average(&(extinction * transmittance));
+            let origin = ray.origin() + distance * ray.direction().unit_vector();
+            let direction = Vector3::random_in_unit_sphere().unit_vector();
+            return Scattered {
+                attenuation: self.albedo * extinction * transmittance / pdf,
+                scattered: Ray::new(origin, direction),
+                kind: BounceKind::Transmission,
+                delta: true,
+            };
+        }
+
+        let transmittance = (-boundary_distance * extinction).exp();
+        let weight = transmittance / average(&transmittance);
+        match self.boundary.scatter(ray, rec) {
+            Scattered {
+                attenuation,
+                scattered,
+                kind,
+                ..
+            } => Scattered {
+                attenuation: weight * attenuation,
+                scattered,
+                kind,
+                delta: true,
+            },
+            absorbed => absorbed,
+        }
+    }
+
+    fn albedo(&self, _rec: &Hit) -> Colour {
+        self.albedo
+    }
+
+    fn name(&self) -> &'static str {
+        "Subsurface"
+    }
+}
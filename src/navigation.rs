@@ -0,0 +1,133 @@
+use crate::camera::CameraSettings;
+use crate::prelude::*;
+
+use macroquad::prelude::*;
+
+const ORBIT_SPEED: f64 = 0.005;
+const PAN_SPEED: f64 = 0.001;
+const ZOOM_FACTOR: f64 = 0.9;
+const FLY_SPEED: f64 = 5.;
+const MAX_PITCH: f64 = 1.55;
+
+/// Turns mouse and keyboard input into changes to the camera settings.
+///
+/// * Left drag orbits around `look_at`, right drag pans and the wheel zooms.
+/// * `W`/`A`/`S`/`D` fly forwards, left, backwards and right; `Q`/`E` fly down and up.
+/// * `[`/`]` change the field of view, `-`/`=` the aperture and `,`/`.` the focus distance.
+#[derive(Default)]
+pub struct Navigator {
+    last_mouse: Option<(f32, f32)>,
+}
+
+impl Navigator {
+    /// Applies this frame's input to `settings`, returning whether anything changed.
+    pub fn update(&mut self, settings: &mut CameraSettings) -> bool {
+        let mut changed = false;
+
+        let mouse = mouse_position();
+        let (dx, dy) = match self.last_mouse {
+            Some((last_x, last_y)) => ((mouse.0 - last_x) as f64, (mouse.1 - last_y) as f64),
+            None => (0., 0.),
+        };
+        self.last_mouse = Some(mouse);
+
+        if dx != 0. || dy != 0. {
+            if is_mouse_button_down(MouseButton::Left) {
+                Self::orbit(settings, dx, dy);
+                changed = true;
+            } else if is_mouse_button_down(MouseButton::Right) {
+                Self::pan(settings, dx, dy);
+                changed = true;
+            }
+        }
+
+        let (_, wheel) = mouse_wheel();
+        if wheel != 0. {
+            let factor = match wheel > 0. {
+                true => ZOOM_FACTOR,
+                false => 1. / ZOOM_FACTOR,
+            };
+            settings.look_from =
+                settings.look_at + (settings.look_from - settings.look_at) * factor;
+            changed = true;
+        }
+
+        changed |= Self::fly(settings);
+        changed |= Self::adjust_lens(settings);
+        changed
+    }
+
+    fn orbit(settings: &mut CameraSettings, dx: f64, dy: f64) {
+        let offset = settings.look_from - settings.look_at;
+        let radius = offset.length();
+        let yaw = offset.x().atan2(offset.z()) - dx * ORBIT_SPEED;
+        let pitch = ((offset.y() / radius).asin() + dy * ORBIT_SPEED).clamp(-MAX_PITCH, MAX_PITCH);
+
+        settings.look_from = settings.look_at
+            + radius
+                * Vector3::new(
+                    pitch.cos() * yaw.sin(),
+                    pitch.sin(),
+                    pitch.cos() * yaw.cos(),
+                );
+    }
+
+    fn pan(settings: &mut CameraSettings, dx: f64, dy: f64) {
+        let offset = settings.look_from - settings.look_at;
+        let w = offset.unit_vector();
+        let u = settings.up_vector.cross(&w).unit_vector();
+        let v = w.cross(&u);
+
+        let shift = (-dx * u + dy * v) * offset.length() * PAN_SPEED;
+        settings.look_from += shift;
+        settings.look_at += shift;
+    }
+
+    fn fly(settings: &mut CameraSettings) -> bool {
+        let forward = (settings.look_at - settings.look_from).unit_vector();
+        let right = forward.cross(&settings.up_vector).unit_vector();
+        let up = settings.up_vector.unit_vector();
+
+        let mut direction = Vector3::default();
+        for (key, axis) in [
+            (KeyCode::W, forward),
+            (KeyCode::S, -forward),
+            (KeyCode::D, right),
+            (KeyCode::A, -right),
+            (KeyCode::E, up),
+            (KeyCode::Q, -up),
+        ] {
+            if is_key_down(key) {
+                direction += axis;
+            }
+        }
+
+        if direction.near_zero() {
+            return false;
+        }
+        let shift = direction * FLY_SPEED * get_frame_time() as f64;
+        settings.look_from += shift;
+        settings.look_at += shift;
+        true
+    }
+
+    fn adjust_lens(settings: &mut CameraSettings) -> bool {
+        let mut changed = true;
+        if is_key_pressed(KeyCode::LeftBracket) {
+            settings.v_fov = (settings.v_fov - 2.).max(2.);
+        } else if is_key_pressed(KeyCode::RightBracket) {
+            settings.v_fov = (settings.v_fov + 2.).min(120.);
+        } else if is_key_pressed(KeyCode::Minus) {
+            settings.aperture = (settings.aperture - 0.05).max(0.);
+        } else if is_key_pressed(KeyCode::Equal) {
+            settings.aperture += 0.05;
+        } else if is_key_pressed(KeyCode::Comma) {
+            settings.focus_dist = (settings.focus_dist - 0.5).max(0.5);
+        } else if is_key_pressed(KeyCode::Period) {
+            settings.focus_dist += 0.5;
+        } else {
+            changed = false;
+        }
+        changed
+    }
+}
@@ -0,0 +1,149 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::integrator::{find_integrator, IntegratorKind};
+use crate::scene::find_scene;
+use crate::settings::RenderSettings;
+
+pub struct Options {
+    pub headless: bool,
+    pub output: PathBuf,
+    pub denoise: bool,
+    pub scene: String,
+    pub settings: RenderSettings,
+    pub checkpoint: Option<PathBuf>,
+    pub checkpoint_interval: u64,
+    pub resume: bool,
+    pub coordinator: Option<String>,
+    pub worker: Option<String>,
+    pub tile_size: u32,
+    pub tile_timeout: u64,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            headless: false,
+            output: PathBuf::from("image.ppm"),
+            denoise: false,
+            scene: "cover".into(),
+            settings: RenderSettings::default(),
+            checkpoint: None,
+            checkpoint_interval: 60,
+            resume: false,
+            coordinator: None,
+            worker: None,
+            tile_size: 32,
+            tile_timeout: 300,
+        }
+    }
+}
+
+impl Options {
+    pub fn from_args() -> Result<Options, String> {
+        let mut options = Options::default();
+        let mut args = std::env::args().skip(1);
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--headless" => options.headless = true,
+                "--denoise" => options.denoise = true,
+                "--spectral" => options.settings.spectral = true,
+                "--output" => options.output = Self::parse(&arg, args.next())?,
+                "--width" => options.settings.width = Self::parse(&arg, args.next())?,
+                "--samples" => options.settings.samples_per_pixel = Self::parse(&arg, args.next())?,
+                "--depth" => options.settings.max_depth = Self::parse(&arg, args.next())?,
+                "--diffuse-depth" => {
+                    options.settings.diffuse_depth = Self::parse(&arg, args.next())?
+                }
+                "--specular-depth" => {
+                    options.settings.specular_depth = Self::parse(&arg, args.next())?
+                }
+                "--transmission-depth" => {
+                    options.settings.transmission_depth = Self::parse(&arg, args.next())?
+                }
+                "--roulette-depth" => {
+                    options.settings.roulette_depth = Self::parse(&arg, args.next())?
+                }
+                "--threads" => options.settings.threads = Self::parse(&arg, args.next())?,
+                "--seed" => options.settings.seed = Self::parse(&arg, args.next())?,
+                "--time-limit" => {
+                    let seconds: f64 = Self::parse(&arg, args.next())?;
+                    if !(seconds > 0. && seconds.is_finite()) {
+                        return Err("--time-limit must be a positive number of seconds".into());
+                    }
+                    options.settings.time_limit = Some(Duration::from_secs_f64(seconds));
+                }
+                "--target-error" => {
+                    let target_error: f64 = Self::parse(&arg, args.next())?;
+                    if !(target_error > 0. && target_error.is_finite()) {
+                        return Err("--target-error must be a positive number".into());
+                    }
+                    options.settings.target_error = Some(target_error);
+                }
+                "--checkpoint" => options.checkpoint = Some(Self::parse(&arg, args.next())?),
+                "--checkpoint-interval" => {
+                    options.checkpoint_interval = Self::parse(&arg, args.next())?
+                }
+                "--resume" => options.resume = true,
+                "--coordinator" => {
+                    options.coordinator = Some(Self::parse(&arg, args.next())?);
+                    options.headless = true;
+                }
+                "--worker" => options.worker = Some(Self::parse(&arg, args.next())?),
+                "--tile-size" => options.tile_size = Self::parse(&arg, args.next())?,
+                "--tile-timeout" => options.tile_timeout = Self::parse(&arg, args.next())?,
+                "--scene" => {
+                    options.scene = Self::parse(&arg, args.next())?;
+                    if find_scene(&options.scene).is_none() {
+                        return Err(format!("Unknown scene '{}'", options.scene));
+                    }
+                }
+                "--integrator" => {
+                    let name: String = Self::parse(&arg, args.next())?;
+                    options.settings.integrator = find_integrator(&name)
+                        .ok_or_else(|| format!("Unknown integrator '{}'", name))?;
+                }
+                _ => return Err(format!("Unknown argument '{}'", arg)),
+            }
+        }
+
+        if (options.checkpoint.is_some() || options.resume) && !options.headless {
+            return Err("Checkpoints are only supported by headless renders".into());
+        }
+        if options.coordinator.is_some() && options.checkpoint.is_some() {
+            return Err("Checkpoints are not supported by distributed renders".into());
+        }
+        if options.coordinator.is_some() && options.settings.is_adaptive() {
+            return Err(
+                "--time-limit and --target-error are not supported by distributed renders".into(),
+            );
+        }
+        if options.coordinator.is_some()
+            && options.settings.integrator == IntegratorKind::Bidirectional
+        {
+            return Err("--integrator bdpt is not supported by distributed renders".into());
+        }
+        if options.settings.threads == 0 {
+            return Err("--threads must be at least 1".into());
+        }
+        if options.tile_size == 0 {
+            return Err("--tile-size must be at least 1".into());
+        }
+        if options.tile_timeout == 0 {
+            return Err("--tile-timeout must be at least 1".into());
+        }
+        if options.resume && options.checkpoint.is_none() {
+            return Err("--resume needs a --checkpoint file to resume from".into());
+        }
+        Ok(options)
+    }
+
+    fn parse<T: FromStr>(arg: &str, value: Option<String>) -> Result<T, String> {
+        value
+            .ok_or_else(|| format!("{} expects a value", arg))?
+            .parse()
+            .map_err(|_| format!("{} was given an invalid value", arg))
+    }
+}
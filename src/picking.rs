@@ -0,0 +1,47 @@
+use std::fmt;
+
+use crate::prelude::*;
+
+pub struct Pick {
+    pub object: usize,
+    pub material: &'static str,
+    pub p: Point3,
+    pub normal: Vector3,
+    pub distance: f64,
+}
+
+impl fmt::Display for Pick {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "object {} ({}) at {} normal {} distance {:.3}",
+            self.object, self.material, self.p, self.normal, self.distance
+        )
+    }
+}
+
+/// Finds the closest object along `ray`, reporting its index within the world.
+pub fn pick(world: &[Box<dyn Hittable>], ray: &Ray) -> Option<Pick> {
+    let mut rec: Hit = Default::default();
+    let mut closest: Option<(usize, Hit)> = None;
+    let mut closest_so_far = f64::INFINITY;
+
+    for (index, object) in world.iter().enumerate() {
+        if object.hit(ray, 0.0001, closest_so_far, &mut rec) {
+            closest_so_far = rec.t;
+            closest = Some((index, rec.clone()));
+        }
+    }
+
+    closest.map(|(object, rec)| Pick {
+        object,
+        material: rec
+            .material
+            .as_ref()
+            .map(|mat| mat.name())
+            .unwrap_or("None"),
+        p: rec.p,
+        normal: rec.normal,
+        distance: rec.t * ray.direction().length(),
+    })
+}
@@ -1,14 +1,32 @@
+use crate::framebuffer::Features;
 use crate::prelude::*;
+use crate::scene::Scene;
+use crate::spectrum::Wavelengths;
 
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct Ray {
     origin: Point3,
     direction: Vector3,
+    wavelengths: Option<Wavelengths>,
 }
 
 impl Ray {
     pub fn new(origin: Point3, direction: Vector3) -> Self {
-        Self { origin, direction }
+        Self {
+            origin,
+            direction,
+            wavelengths: None,
+        }
+    }
+
+    /// Only set on rays traced in spectral mode.
+    pub fn with_wavelengths(mut self, wavelengths: Wavelengths) -> Self {
+        self.wavelengths = Some(wavelengths);
+        self
+    }
+
+    pub fn wavelengths(&self) -> Option<&Wavelengths> {
+        self.wavelengths.as_ref()
     }
 
     pub fn origin(&self) -> Point3 {
@@ -23,32 +41,12 @@ impl Ray {
         self.origin + self.direction * t
     }
 
-    pub fn colour(&self, world: &dyn Hittable, depth: u8) -> Colour {
-        if depth == 0 {
-            return Colour::default();
-        }
-
+    pub fn features(&self, scene: &Scene) -> Features {
         let mut rec: Hit = Default::default();
 
-        if world.hit(self, 0.0001, f64::INFINITY, &mut rec) {
-            return if let Some(mat) = &rec.material.as_ref() {
-                match mat.scatter(self, &rec) {
-                    ScatterResult::Scattered {
-                        attenuation,
-                        scattered,
-                    } => attenuation * scattered.colour(world, depth - 1),
-                    ScatterResult::Absorbed {
-                        attenuation: _attenuation,
-                        scattered: _scattered,
-                    } => Colour::default(),
-                }
-            } else {
-                Colour::default()
-            };
+        match scene.hit(self, 0.0001, f64::INFINITY, &mut rec) {
+            true => Features::from_hit(&rec),
+            false => Features::from_miss(scene, self),
         }
-
-        let unit_direction = self.direction().unit_vector();
-        let t = 0.5 * (unit_direction.y() + 1.0);
-        (1. - t) * Colour::new(1, 1, 1) + t * Colour::new(0.5, 0.7, 1)
     }
 }
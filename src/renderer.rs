@@ -1,112 +1,179 @@
+use std::io;
 use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::Duration;
 
-use crate::camera::Camera;
-use crate::canvas::Canvas;
+use crate::camera::CameraSettings;
+use crate::checkpoint::{fingerprint, read_checkpoint, CheckpointHeader, Checkpointer};
+use crate::denoiser::Denoiser;
+use crate::distributed::{render_distributed, JobDescription};
+use crate::framebuffer::{write_ppm, FrameBuffer};
+use crate::image::Image;
+use crate::job::{JobKind, RenderJob};
+use crate::options::Options;
 use crate::scene::*;
-use crate::vector3::{Colour, Point3, Vector3};
+use crate::settings::RenderSettings;
+use crate::stats::Stats;
 
-use ::rand::prelude::*;
-use macroquad::prelude::*;
-use num_cpus::get_physical;
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
 
-pub async fn run(aspect_ratio: f64) {
-    let canvas = Canvas::default();
-    let canvas_width = canvas.width();
-    let canvas_height = canvas.height();
-    let canvas_ref = Arc::new(Mutex::new(canvas));
-    let world_ref = Arc::new(build_cover());
+pub type ProgressCallback = Box<dyn Fn(&Stats) + Send + Sync>;
 
-    let look_from = Point3::new(13, 2, 3);
-    let look_at = Point3::new(0, 0, 0);
-    let up_vector = Vector3::new(0, 1, 0);
-    let dist_to_focus = 10.;
-    let aperture = 0.1;
+/// Renders scenes into images, for programs embedding rtiow.
+pub struct Renderer {
+    settings: RenderSettings,
+    camera: CameraSettings,
+    denoise: bool,
+    progress: Option<ProgressCallback>,
+}
 
-    let camera = Camera::new(
-        look_from,
-        look_at,
-        up_vector,
-        20,
-        aspect_ratio,
-        aperture,
-        dist_to_focus,
-    );
-    let camera_ref = Arc::new(camera);
+impl Renderer {
+    pub fn new(settings: RenderSettings) -> Self {
+        Self {
+            settings,
+            camera: CameraSettings::default(),
+            denoise: false,
+            progress: None,
+        }
+    }
 
-    let cpu_cores = get_physical() as u32;
-    let mut launched_threads = vec![];
-    for thread_id in 0..cpu_cores {
-        let canvas_local = canvas_ref.clone();
-        let world_local = world_ref.clone();
-        let camera_local = camera_ref.clone();
+    /// Sets the viewpoint, which is built for the aspect ratio of the image.
+    pub fn camera(mut self, camera: CameraSettings) -> Self {
+        self.camera = camera;
+        self
+    }
 
-        let thread_handle = thread::spawn(move || {
-            let mut rng = thread_rng();
+    pub fn denoise(mut self, denoise: bool) -> Self {
+        self.denoise = denoise;
+        self
+    }
 
-            let samples_per_pixel = 20u32;
-            let max_depth = 50u8;
+    /// Called from the rendering thread every 100ms while a render runs, and
+    /// once more when it completes.
+    pub fn on_progress(mut self, progress: impl Fn(&Stats) + Send + Sync + 'static) -> Self {
+        self.progress = Some(Box::new(progress));
+        self
+    }
 
-            for y in (0..=canvas_height - thread_id - 1)
-                .rev()
-                .step_by(cpu_cores as usize)
-            {
-                for x in 0..canvas_width {
-                    let mut pixel_colour = Colour::default();
-                    for _ in 0..samples_per_pixel {
-                        let u = (x as f64 + rng.gen::<f64>()) / (canvas_width - 1) as f64;
-                        let v = (y as f64 + rng.gen::<f64>()) / (canvas_height - 1) as f64;
-                        let ray = camera_local.get_ray(u, v);
-                        pixel_colour += ray.colour(&world_local.as_ref(), max_depth);
-                    }
-                    canvas_local
-                        .lock()
-                        .expect("Something went wrong inside the canvas lock")
-                        .set_pixel(x, y, pixel_colour, samples_per_pixel);
-                }
-            }
-        });
-        launched_threads.push((thread_handle, true));
+    pub fn settings(&self) -> &RenderSettings {
+        &self.settings
     }
 
-    let font_size = 32;
-    let rendering_text = "Tracing Rays...";
-    let text_dimensions = measure_text(rendering_text, None, font_size, 1.0);
-    let text_x = (screen_width() - text_dimensions.width) / 2.;
-    let text_y = (screen_height() - text_dimensions.height) / 2.;
+    /// Renders the scene, blocking until the image is complete.
+    pub fn render(&self, scene: &Scene) -> Image {
+        let (width, height) = (self.settings.width, self.settings.height);
+        let framebuffer_ref = Arc::new(Mutex::new(FrameBuffer::new(width, height)));
+        let job = RenderJob::start(
+            framebuffer_ref.clone(),
+            scene.clone(),
+            self.camera.build(self.settings.aspect_ratio()),
+            self.settings,
+            JobKind::Full,
+        );
+        while !job.is_finished() {
+            thread::sleep(PROGRESS_INTERVAL);
+            if let Some(progress) = &self.progress {
+                progress(job.stats());
+            }
+        }
+        let stats = job.wait();
+        if let Some(progress) = &self.progress {
+            progress(&stats);
+        }
 
-    let mut threads_running = true;
-    loop {
-        clear_background(WHITE);
-        canvas_ref
+        let framebuffer = framebuffer_ref
             .lock()
-            .expect("Something went wrong inside the canvas lock")
-            .render();
-        if threads_running {
-            draw_rectangle(
-                text_x - 8.,
-                text_y - text_dimensions.height / 2. - 16.,
-                text_dimensions.width + 16.,
-                text_dimensions.height + 16.,
-                WHITE,
-            );
-            draw_text(rendering_text, text_x, text_y, font_size as f32, RED);
-        }
-        next_frame().await;
-        if threads_running {
-            let mut active_count = 0;
-            for (thread_handle, is_running) in launched_threads.iter_mut() {
-                if *is_running {
-                    if thread_handle.is_finished() {
-                        *is_running = false
-                    } else {
-                        active_count += 1;
-                    }
-                }
-            }
-            if active_count == 0 {
-                threads_running = false;
+            .expect("Something went wrong inside the framebuffer lock");
+        let pixels = match self.denoise {
+            true => Denoiser::default().denoise(&framebuffer),
+            false => framebuffer.pixels(),
+        };
+        Image::new(width, height, pixels)
+    }
+}
+
+pub fn render_headless(aspect_ratio: f64, options: Options) -> io::Result<()> {
+    let mut settings = options.settings;
+    settings.height = (settings.width as f64 / aspect_ratio) as u32;
+    let (width, height) = (settings.width, settings.height);
+    let build_scene = find_scene(&options.scene).unwrap_or(build_cover);
+    let scene = build_scene();
+    let camera_settings = CameraSettings::default();
+
+    let header = CheckpointHeader {
+        width,
+        height,
+        scene_hash: fingerprint(&scene),
+        // The thread count doesn't change the image, so it may differ on resume.
+        settings_hash: fingerprint(&(
+            RenderSettings {
+                threads: 0,
+                ..settings
+            },
+            &camera_settings,
+        )),
+        seed: settings.seed,
+    };
+    let (framebuffer, kind) = match (&options.checkpoint, options.resume) {
+        (Some(path), true) if path.exists() => (read_checkpoint(path, &header)?, JobKind::Continue),
+        _ => (FrameBuffer::new(width, height), JobKind::Full),
+    };
+    let framebuffer_ref = Arc::new(Mutex::new(framebuffer));
+    let mut checkpointer = options.checkpoint.clone().map(|path| {
+        Checkpointer::new(
+            path,
+            header,
+            Duration::from_secs(options.checkpoint_interval),
+        )
+    });
+
+    if let Some(address) = &options.coordinator {
+        let description = JobDescription {
+            scene: options.scene.clone(),
+            aspect_ratio,
+            settings,
+            camera: camera_settings,
+            tile_timeout: Duration::from_secs(options.tile_timeout),
+        };
+        render_distributed(
+            address,
+            framebuffer_ref.clone(),
+            description,
+            options.tile_size,
+        )?;
+    } else {
+        let job = RenderJob::start(
+            framebuffer_ref.clone(),
+            scene,
+            camera_settings.build(aspect_ratio),
+            settings,
+            kind,
+        );
+        while !job.is_finished() {
+            thread::sleep(PROGRESS_INTERVAL);
+            if let Some(checkpointer) = checkpointer.as_mut() {
+                checkpointer.tick(&framebuffer_ref)?;
             }
         }
+        println!("{}", job.wait());
+        if let Some(checkpointer) = checkpointer.as_mut() {
+            checkpointer.write(&framebuffer_ref)?;
+        }
+    }
+
+    let framebuffer = framebuffer_ref
+        .lock()
+        .expect("Something went wrong inside the framebuffer lock");
+    println!(
+        "Achieved {:.1} samples per pixel",
+        framebuffer.mean_samples_per_pixel()
+    );
+    if let Some(error) = framebuffer.mean_relative_error() {
+        println!("Estimated mean relative error: {:.4}", error);
     }
+    let pixels = match options.denoise {
+        true => Denoiser::default().denoise(&framebuffer),
+        false => framebuffer.pixels(),
+    };
+    write_ppm(&options.output, width, height, &pixels)
 }
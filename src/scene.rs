@@ -1,11 +1,173 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 
+use crate::bvh::Bvh;
+use crate::light_tree::LightTree;
 use crate::prelude::*;
+use crate::texture::{Checker, ImageTexture, Noise};
 
+use num_traits::NumCast;
 use rand::{prelude::StdRng, Rng, SeedableRng};
 
-#[allow(dead_code)]
-pub fn build_test() -> Vec<Box<dyn Hittable>> {
+pub type BuildScene = fn() -> Scene;
+
+pub const SCENES: [(&str, BuildScene); 14] = [
+    ("cover", build_cover),
+    ("test", build_test),
+    ("metals", build_metals),
+    ("glass", build_glass),
+    ("dispersion", build_dispersion),
+    ("principled", build_principled),
+    ("layered", build_layered),
+    ("bumps", build_bumps),
+    ("subsurface", build_subsurface),
+    ("lights", build_lights),
+    ("cover-sky", build_cover_sky),
+    ("studio", build_studio),
+    ("glowing", build_glowing),
+    ("caustics", build_caustics),
+];
+
+pub fn find_scene(name: &str) -> Option<BuildScene> {
+    SCENES
+        .iter()
+        .find(|(scene_name, _)| *scene_name == name)
+        .map(|(_, builder)| *builder)
+}
+
+/// The objects and lights to be rendered. Cloning a scene shares them.
+#[derive(Clone, Debug)]
+pub struct Scene {
+    world: Arc<Vec<Box<dyn Hittable>>>,
+    bvh: Arc<Bvh>,
+    /// Each material's position in the order objects first use them, keyed
+    /// by its address.
+    material_ids: Arc<HashMap<usize, usize>>,
+    lights: Arc<Vec<Box<dyn Light>>>,
+    environment: Arc<dyn Environment>,
+    emitters: Arc<LightTree>,
+}
+
+impl Scene {
+    pub fn builder() -> SceneBuilder {
+        SceneBuilder::default()
+    }
+
+    /// One of the built in scenes listed in `SCENES`.
+    pub fn named(name: &str) -> Option<Scene> {
+        find_scene(name).map(|build_scene| build_scene())
+    }
+
+    pub fn world(&self) -> &Arc<Vec<Box<dyn Hittable>>> {
+        &self.world
+    }
+
+    /// A number for `material` that is the same on every run of the scene, or
+    /// `None` if no object in the world uses it.
+    pub fn material_id(&self, material: &Arc<dyn Material>) -> Option<usize> {
+        self.material_ids.get(&material_address(material)).copied()
+    }
+
+    /// Lights that aren't part of the geometry, such as point lights.
+    pub fn lights(&self) -> &[Box<dyn Light>] {
+        &self.lights
+    }
+
+    /// What rays see when they leave the scene.
+    pub fn environment(&self) -> &dyn Environment {
+        self.environment.as_ref()
+    }
+
+    /// The objects that give off light, for aiming shadow rays at.
+    pub fn emitters(&self) -> &LightTree {
+        &self.emitters
+    }
+
+    pub fn with_environment(mut self, environment: impl Environment + 'static) -> Self {
+        self.environment = Arc::new(environment);
+        self
+    }
+}
+
+impl Hittable for Scene {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64, rec: &mut Hit) -> bool {
+        self.bvh.hit(&self.world, ray, t_min, t_max, rec)
+    }
+}
+
+impl From<Vec<Box<dyn Hittable>>> for Scene {
+    fn from(world: Vec<Box<dyn Hittable>>) -> Self {
+        Self {
+            emitters: Arc::new(LightTree::new(&world)),
+            bvh: Arc::new(Bvh::new(&world)),
+            material_ids: Arc::new(material_ids(&world)),
+            world: Arc::new(world),
+            lights: Arc::default(),
+            environment: Arc::new(Gradient),
+        }
+    }
+}
+
+fn material_address(material: &Arc<dyn Material>) -> usize {
+    Arc::as_ptr(material) as *const () as usize
+}
+
+/// Numbers the materials in the order the world's objects first use them.
+fn material_ids(world: &[Box<dyn Hittable>]) -> HashMap<usize, usize> {
+    let mut ids = HashMap::new();
+    for material in world.iter().filter_map(|object| object.material()) {
+        let next_id = ids.len();
+        ids.entry(material_address(material)).or_insert(next_id);
+    }
+    ids
+}
+
+#[derive(Default)]
+pub struct SceneBuilder {
+    world: Vec<Box<dyn Hittable>>,
+    lights: Vec<Box<dyn Light>>,
+    environment: Option<Arc<dyn Environment>>,
+}
+
+impl SceneBuilder {
+    pub fn object(mut self, object: impl Hittable + 'static) -> Self {
+        self.world.push(Box::new(object));
+        self
+    }
+
+    pub fn light(mut self, light: impl Light + 'static) -> Self {
+        self.lights.push(Box::new(light));
+        self
+    }
+
+    /// Replaces the default gradient background.
+    pub fn environment(mut self, environment: impl Environment + 'static) -> Self {
+        self.environment = Some(Arc::new(environment));
+        self
+    }
+
+    pub fn sphere<T: NumCast>(
+        self,
+        center: Point3,
+        radius: T,
+        material: Arc<dyn Material>,
+    ) -> Self {
+        self.object(Sphere::new(center, radius, material))
+    }
+
+    pub fn build(self) -> Scene {
+        Scene {
+            emitters: Arc::new(LightTree::new(&self.world)),
+            bvh: Arc::new(Bvh::new(&self.world)),
+            material_ids: Arc::new(material_ids(&self.world)),
+            world: Arc::new(self.world),
+            lights: Arc::new(self.lights),
+            environment: self.environment.unwrap_or_else(|| Arc::new(Gradient)),
+        }
+    }
+}
+
+pub fn build_test() -> Scene {
     let mut world: Vec<Box<dyn Hittable>> = vec![];
 
     let material_ground = Arc::new(Lambertian::new(Colour::new(0.8, 0.8, 0.)));
@@ -18,10 +180,10 @@ pub fn build_test() -> Vec<Box<dyn Hittable>> {
     world.push(Sphere::new(Point3::new(-1, 0, -1), 0.5, material_left.clone()).to_box());
     world.push(Sphere::new(Point3::new(-1, 0, -1), -0.45, material_left).to_box());
     world.push(Sphere::new(Point3::new(1, 0, -1), 0.5, material_right).to_box());
-    world
+    world.into()
 }
 
-pub fn build_cover() -> Vec<Box<dyn Hittable>> {
+pub fn build_cover() -> Scene {
     let mut world: Vec<Box<dyn Hittable>> = vec![];
 
     let material_ground = Arc::new(Lambertian::new(Colour::new(0.5, 0.5, 0.5)));
@@ -65,5 +227,441 @@ pub fn build_cover() -> Vec<Box<dyn Hittable>> {
     let material3 = Arc::new(Metal::new(Colour::new(0.7, 0.6, 0.5)));
     world.push(Sphere::new(Point3::new(4, 1, 0), 1.0, material3).to_box());
 
-    world
+    world.into()
+}
+
+pub fn build_metals() -> Scene {
+    let mut world: Vec<Box<dyn Hittable>> = vec![];
+
+    let material_ground = Arc::new(Lambertian::new(Colour::new(0.5, 0.5, 0.5)));
+    world.push(Sphere::new(Point3::new(0, -1000, 0), 1000, material_ground).to_box());
+
+    let aluminium = Conductor::aluminium(0.);
+    let brushed_aluminium = Conductor::aluminium_anisotropic(0.1, 0.5);
+    let metals: [Arc<dyn Material>; 5] = [
+        Arc::new(Conductor::gold(0.05)),
+        Arc::new(Conductor::copper(0.3)),
+        Arc::new(brushed_aluminium),
+        Arc::new(Conductor::silver(0.2)),
+        Arc::new(aluminium),
+    ];
+    for (index, material) in metals.into_iter().enumerate() {
+        let center = Point3::new(-4. + 2. * index as f64, 0.9, 0);
+        world.push(Sphere::new(center, 0.9, material).to_box());
+    }
+    world.into()
+}
+
+pub fn build_glass() -> Scene {
+    let mut world: Vec<Box<dyn Hittable>> = vec![];
+
+    let material_ground = Arc::new(Lambertian::new(Colour::new(0.5, 0.5, 0.5)));
+    world.push(Sphere::new(Point3::new(0, -1000, 0), 1000, material_ground).to_box());
+
+    let clear = Arc::new(Dielectric::new(1.5));
+    world.push(Sphere::new(Point3::new(-4, 0.9, 0), 0.9, clear).to_box());
+
+    let frosted = Arc::new(Dielectric::new(1.5).with_roughness(0.3));
+    world.push(Sphere::new(Point3::new(-2, 0.9, 0), 0.9, frosted).to_box());
+
+    let tinted = || Arc::new(Dielectric::new(1.5).with_tint(Colour::new(0.2, 0.7, 0.3), 0.5));
+    world.push(Sphere::new(Point3::new(0, 0.9, 0), 0.9, tinted()).to_box());
+
+    // A thin shell of the same glass hardly tints at all.
+    let shell = tinted();
+    world.push(Sphere::new(Point3::new(2, 0.9, 0), 0.9, shell.clone()).to_box());
+    world.push(Sphere::new(Point3::new(2, 0.9, 0), -0.85, shell).to_box());
+
+    let frosted_tinted = Arc::new(
+        Dielectric::new(1.5)
+            .with_roughness(0.15)
+            .with_tint(Colour::new(0.8, 0.4, 0.2), 1.),
+    );
+    world.push(Sphere::new(Point3::new(4, 0.9, 0), 0.9, frosted_tinted).to_box());
+    world.into()
+}
+
+/// Dispersive glass only splits colours when rendered with `--spectral`.
+pub fn build_dispersion() -> Scene {
+    let mut world: Vec<Box<dyn Hittable>> = vec![];
+
+    let material_ground = Arc::new(Lambertian::new(Colour::new(0.5, 0.5, 0.5)));
+    world.push(Sphere::new(Point3::new(0, -1000, 0), 1000, material_ground).to_box());
+
+    let crown = Arc::new(Dielectric::crown_glass());
+    world.push(Sphere::new(Point3::new(-4, 1, 0), 1, crown).to_box());
+
+    let flint = Arc::new(Dielectric::flint_glass());
+    world.push(Sphere::new(Point3::new(0, 1, 0), 1, flint).to_box());
+
+    let diamond = Arc::new(Dielectric::diamond());
+    world.push(Sphere::new(Point3::new(4, 1, 0), 1, diamond).to_box());
+    world.into()
+}
+
+pub fn build_principled() -> Scene {
+    let mut world: Vec<Box<dyn Hittable>> = vec![];
+
+    let material_ground = Arc::new(Principled::new(Checker::new(
+        Colour::new(0.2, 0.3, 0.1),
+        Colour::new(0.9, 0.9, 0.9),
+        3.,
+    )));
+    world.push(Sphere::new(Point3::new(0, -1000, 0), 1000, material_ground).to_box());
+
+    let plastic = Principled::new(Colour::new(0.8, 0.1, 0.1)).roughness(0.3);
+    let brushed_gold = Principled::new(Colour::new(1.0, 0.77, 0.34))
+        .metallic(1.)
+        .roughness(0.25);
+    let car_paint = Principled::new(Colour::new(0.05, 0.1, 0.5))
+        .roughness(0.6)
+        .clearcoat(1.)
+        .clearcoat_roughness(0.03);
+    let velvet = Principled::new(Colour::new(0.4, 0.05, 0.3))
+        .roughness(1.)
+        .sheen(1.);
+    let frosted_glass = Principled::new(Colour::new(0.8, 1.0, 0.9))
+        .roughness(0.1)
+        .transmission(1.);
+    // Roughness driven by a texture: polished and matte squares of copper.
+    let patterned = Principled::new(Colour::new(0.95, 0.64, 0.54))
+        .metallic(1.)
+        .roughness(Checker::new(0.05, 0.6, 10.));
+
+    let materials: [Arc<dyn Material>; 6] = [
+        Arc::new(plastic),
+        Arc::new(brushed_gold),
+        Arc::new(car_paint),
+        Arc::new(velvet),
+        Arc::new(frosted_glass),
+        Arc::new(patterned),
+    ];
+    for (index, material) in materials.into_iter().enumerate() {
+        let center = Point3::new(-5. + 2. * index as f64, 0.8, 0);
+        world.push(Sphere::new(center, 0.8, material).to_box());
+    }
+
+    let lamp = Principled::new(Colour::new(0, 0, 0)).emission(Colour::new(4, 3.6, 3));
+    world.push(Sphere::new(Point3::new(0, 3, -3), 0.8, Arc::new(lamp)).to_box());
+    world.into()
+}
+
+pub fn build_layered() -> Scene {
+    let mut world: Vec<Box<dyn Hittable>> = vec![];
+
+    let material_ground = Arc::new(Lambertian::new(Colour::new(0.5, 0.5, 0.5)));
+    world.push(Sphere::new(Point3::new(0, -1000, 0), 1000, material_ground).to_box());
+
+    let glossy_plastic = Arc::new(Plastic::new(Colour::new(0.7, 0.1, 0.1)));
+    world.push(Sphere::new(Point3::new(-4, 1, 0), 1, glossy_plastic).to_box());
+
+    let varnished_wood = Arc::new(Plastic::coated(
+        Checker::new(
+            Colour::new(0.45, 0.25, 0.1),
+            Colour::new(0.3, 0.15, 0.05),
+            12.,
+        ),
+        0.1,
+        1.5,
+    ));
+    world.push(Sphere::new(Point3::new(-2, 1, 0), 1, varnished_wood).to_box());
+
+    // Gold flecks in a matte base, picked out by a checker pattern.
+    let flecked = Arc::new(MixMaterial::new(
+        Arc::new(Lambertian::new(Colour::new(0.1, 0.2, 0.4))),
+        Arc::new(Conductor::gold(0.2)),
+        Checker::new(0., 1., 20.),
+    ));
+    world.push(Sphere::new(Point3::new(0, 1, 0), 1, flecked).to_box());
+
+    let half_metal = Arc::new(MixMaterial::new(
+        Arc::new(Plastic::coated(Colour::new(0.1, 0.5, 0.1), 0.3, 1.5)),
+        Arc::new(Conductor::silver(0.1)),
+        0.5,
+    ));
+    world.push(Sphere::new(Point3::new(2, 1, 0), 1, half_metal).to_box());
+
+    let matte_plastic = Arc::new(Plastic::coated(Colour::new(0.9, 0.9, 0.9), 0.5, 1.5));
+    world.push(Sphere::new(Point3::new(4, 1, 0), 1, matte_plastic).to_box());
+    world.into()
+}
+
+pub fn build_bumps() -> Scene {
+    let mut world: Vec<Box<dyn Hittable>> = vec![];
+
+    // A flagstone floor made of two triangles, roughened with noise.
+    let flagstones = Arc::new(BumpMapped::new(
+        Arc::new(Lambertian::new(Colour::new(0.5, 0.45, 0.4))),
+        Noise::new(1.5, 4),
+        0.3,
+    ));
+    for triangle in Triangle::quad(
+        Point3::new(-20, 0, 20),
+        Vector3::new(40, 0, 0),
+        Vector3::new(0, 0, -40),
+        flagstones,
+    ) {
+        world.push(triangle.to_box());
+    }
+
+    let hammered_copper = Arc::new(BumpMapped::new(
+        Arc::new(Conductor::copper(0.15)),
+        Noise::new(6., 2),
+        0.05,
+    ));
+    world.push(Sphere::new(Point3::new(-4, 1, 0), 1, hammered_copper).to_box());
+
+    let orange_peel = Arc::new(BumpMapped::new(
+        Arc::new(Plastic::coated(Colour::new(0.7, 0.2, 0.05), 0.05, 1.5)),
+        Noise::new(20., 3),
+        0.01,
+    ));
+    world.push(Sphere::new(Point3::new(-2, 1, 0), 1, orange_peel).to_box());
+
+    let rippled_glass = Arc::new(BumpMapped::new(
+        Arc::new(Dielectric::new(1.5)),
+        Noise::new(3., 1),
+        0.15,
+    ));
+    world.push(Sphere::new(Point3::new(0, 1, 0), 1, rippled_glass).to_box());
+
+    let rock = Arc::new(BumpMapped::new(
+        Arc::new(Lambertian::new(Colour::new(0.4, 0.4, 0.45))),
+        Noise::new(2., 6),
+        0.5,
+    ));
+    world.push(Sphere::new(Point3::new(2, 1, 0), 1, rock).to_box());
+
+    let smooth = Arc::new(Plastic::coated(Colour::new(0.1, 0.3, 0.6), 0.05, 1.5));
+    world.push(Sphere::new(Point3::new(4, 1, 0), 1, smooth).to_box());
+
+    // A ridge of rock beyond them whose surface is really displaced, so its
+    // outline against the sky is ragged where the bump mapped spheres stay round.
+    let ridge = Arc::new(Lambertian::new(Colour::new(0.45, 0.4, 0.35)));
+    for triangle in Triangle::displaced(
+        Point3::new(-10, -1.5, 6),
+        Vector3::new(4, 0, 0),
+        Vector3::new(0, 0, -12),
+        32,
+        Noise::new(0.5, 4),
+        4.,
+        ridge,
+    ) {
+        world.push(triangle.to_box());
+    }
+    world.into()
+}
+
+/// Translucent objects lit from behind, next to an opaque one for comparison.
+pub fn build_subsurface() -> Scene {
+    let mut world: Vec<Box<dyn Hittable>> = vec![];
+
+    let material_ground = Arc::new(Lambertian::new(Colour::new(0.5, 0.5, 0.5)));
+    world.push(Sphere::new(Point3::new(0, -1000, 0), 1000, material_ground).to_box());
+
+    let opaque = Arc::new(Lambertian::new(Colour::new(0.9, 0.9, 0.88)));
+    world.push(Sphere::new(Point3::new(-4, 1, 0), 1, opaque).to_box());
+
+    let marble = Arc::new(Subsurface::marble());
+    world.push(Sphere::new(Point3::new(-2, 1, 0), 1, marble).to_box());
+
+    let wax = Arc::new(Subsurface::wax());
+    world.push(Sphere::new(Point3::new(0, 1, 0), 1, wax).to_box());
+
+    let skin = Arc::new(Subsurface::skin());
+    world.push(Sphere::new(Point3::new(2, 1, 0), 1, skin).to_box());
+
+    let jade = Arc::new(
+        Subsurface::new(
+            Colour::new(0.5, 0.95, 0.6),
+            Colour::new(0.4, 0.6, 0.45),
+            1.6,
+        )
+        .with_roughness(0.1),
+    );
+    world.push(Sphere::new(Point3::new(4, 1, 0), 1, jade).to_box());
+
+    let lamp = Principled::new(Colour::new(0, 0, 0)).emission(Colour::new(8, 7, 6));
+    world.push(Sphere::new(Point3::new(0, 2.5, -3), 0.7, Arc::new(lamp)).to_box());
+    world.into()
+}
+
+/// Point, spot and directional lights, each casting its own shadows.
+pub fn build_lights() -> Scene {
+    Scene::builder()
+        .sphere(
+            Point3::new(0, -1000, 0),
+            1000,
+            Arc::new(Lambertian::new(Colour::new(0.5, 0.5, 0.5))),
+        )
+        .sphere(
+            Point3::new(-4, 1, 0),
+            1,
+            Arc::new(Lambertian::new(Colour::new(0.8, 0.3, 0.2))),
+        )
+        .sphere(
+            Point3::new(0, 1, 0),
+            1,
+            Arc::new(Principled::new(Colour::new(0.2, 0.4, 0.8)).roughness(0.3)),
+        )
+        .sphere(Point3::new(4, 1, 0), 1, Arc::new(Conductor::gold(0.2)))
+        .light(PointLight::new(
+            Point3::new(-2, 3, 2),
+            Colour::new(10, 8, 6),
+        ))
+        .light(SpotLight::new(
+            Point3::new(3, 5, 3),
+            Point3::new(4, 0, 0),
+            Colour::new(20, 30, 40),
+            25.,
+            15.,
+        ))
+        .light(
+            DirectionalLight::new(Vector3::new(-1, 2, -1), Colour::new(1, 0.9, 0.7))
+                .with_angular_diameter(2.),
+        )
+        .build()
+}
+
+/// The cover scene under a late afternoon sun.
+pub fn build_cover_sky() -> Scene {
+    build_cover().with_environment(Sky::new(30., 60., 3.))
+}
+
+/// Spheres lit by an HDR environment map of a photo studio, with a small,
+/// very bright key light and two softboxes.
+pub fn build_studio() -> Scene {
+    Scene::builder()
+        .sphere(
+            Point3::new(0, -1000, 0),
+            1000,
+            Arc::new(Lambertian::new(Colour::new(0.5, 0.5, 0.5))),
+        )
+        .sphere(
+            Point3::new(-4, 1, 0),
+            1,
+            Arc::new(Lambertian::new(Colour::new(0.7, 0.7, 0.7))),
+        )
+        .sphere(
+            Point3::new(-2, 1, 0),
+            1,
+            Arc::new(Plastic::coated(Colour::new(0.6, 0.05, 0.05), 0.2, 1.5)),
+        )
+        .sphere(Point3::new(0, 1, 0), 1, Arc::new(Conductor::gold(0.15)))
+        .sphere(
+            Point3::new(2, 1, 0),
+            1,
+            Arc::new(Dielectric::new(1.5).with_roughness(0.2)),
+        )
+        .sphere(
+            Point3::new(4, 1, 0),
+            1,
+            Arc::new(
+                Principled::new(Colour::new(0.1, 0.2, 0.5))
+                    .roughness(0.5)
+                    .clearcoat(1.),
+            ),
+        )
+        .environment(EnvironmentMap::new(studio_map()))
+        .build()
+}
+
+/// Paints the studio's surroundings, as a stand-in for a photographed HDRI.
+fn studio_map() -> ImageTexture {
+    let (width, height) = (512, 256);
+    // Rectangles of light by their corners in the map's u and v.
+    let lights = [
+        ((0.070, 0.292), (0.078, 0.308), Colour::new(800, 750, 680)),
+        ((0.760, 0.300), (0.830, 0.420), Colour::new(6, 6, 6.5)),
+        ((0.290, 0.280), (0.350, 0.400), Colour::new(2, 2.2, 2.5)),
+    ];
+    let mut pixels = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            let (u, v) = (
+                (x as f64 + 0.5) / width as f64,
+                (y as f64 + 0.5) / height as f64,
+            );
+            let light = lights
+                .iter()
+                .find(|((u0, v0), (u1, v1), _)| (*u0..*u1).contains(&u) && (*v0..*v1).contains(&v));
+            pixels.push(match light {
+                Some((_, _, radiance)) => *radiance,
+                None => match v < 0.5 {
+                    true => Colour::new(0.1, 0.1, 0.1) * (1. - v),
+                    false => Colour::new(0.03, 0.03, 0.03),
+                },
+            });
+        }
+    }
+    ImageTexture::from_pixels(width, height, pixels)
+}
+
+/// The cover scene at night with hundreds of the small spheres glowing, to
+/// show off sampling many lights.
+pub fn build_glowing() -> Scene {
+    let mut world: Vec<Box<dyn Hittable>> = vec![];
+
+    let material_ground = Arc::new(Lambertian::new(Colour::new(0.5, 0.5, 0.5)));
+    world.push(Sphere::new(Point3::new(0, -1000, 0), 1000, material_ground).to_box());
+
+    let mut rng = StdRng::seed_from_u64(52);
+    for a in -11..11 {
+        for b in -11..11 {
+            let choose_mat = rng.gen::<f64>();
+            let center = Point3::new(
+                a as f64 + (0.9 * rng.gen::<f64>()),
+                0.2,
+                b as f64 + (0.9 * rng.gen::<f64>()),
+            );
+
+            if (center - Point3::new(4, 0.2, 0)).length() > 0.9 {
+                let material: Arc<dyn Material> = if choose_mat < 0.45 {
+                    let emission = Colour::random_range_from_rng(&mut rng, 0.2, 1.);
+                    Arc::new(Principled::new(Colour::new(0.8, 0.8, 0.8)).emission(4. * emission))
+                } else if choose_mat < 0.85 {
+                    let albedo =
+                        Colour::random_from_rng(&mut rng) * Colour::random_from_rng(&mut rng);
+                    Arc::new(Lambertian::new(albedo))
+                } else {
+                    let albedo = Colour::random_range_from_rng(&mut rng, 0.5, 1.);
+                    Arc::new(Principled::new(albedo).metallic(1.).roughness(0.2))
+                };
+                world.push(Sphere::new(center, 0.2, material).to_box());
+            }
+        }
+    }
+
+    let material1 = Arc::new(Dielectric::new(1.5));
+    world.push(Sphere::new(Point3::new(0, 1, 0), 1.0, material1).to_box());
+
+    let material2 = Arc::new(Principled::new(Colour::new(0.4, 0.2, 0.1)).roughness(0.4));
+    world.push(Sphere::new(Point3::new(-4, 1, 0), 1.0, material2).to_box());
+
+    let material3 = Arc::new(Conductor::silver(0.1));
+    world.push(Sphere::new(Point3::new(4, 1, 0), 1.0, material3).to_box());
+
+    Scene::from(world).with_environment(Uniform::new(Colour::new(0.01, 0.012, 0.02)))
+}
+
+/// A glass ball on the floor under a small, bright lamp, with little else to
+/// light the scene, so nearly all of the light on the floor is focused
+/// through the glass.
+pub fn build_caustics() -> Scene {
+    let mut world: Vec<Box<dyn Hittable>> = vec![];
+
+    let material_ground = Arc::new(Lambertian::new(Colour::new(0.6, 0.6, 0.6)));
+    world.push(Sphere::new(Point3::new(0, -1000, 0), 1000, material_ground).to_box());
+
+    let glass = Arc::new(Dielectric::new(1.5));
+    world.push(Sphere::new(Point3::new(0, 1, 0), 1.0, glass).to_box());
+
+    let matte = Arc::new(Lambertian::new(Colour::new(0.7, 0.3, 0.2)));
+    world.push(Sphere::new(Point3::new(-3, 0.6, -1.5), 0.6, matte).to_box());
+
+    let lamp =
+        Arc::new(Principled::new(Colour::new(0.8, 0.8, 0.8)).emission(Colour::new(150, 140, 120)));
+    world.push(Sphere::new(Point3::new(-2.5, 4.5, -0.5), 0.2, lamp).to_box());
+
+    Scene::from(world).with_environment(Uniform::new(Colour::new(0.005, 0.005, 0.008)))
 }
@@ -0,0 +1,79 @@
+use std::time::Duration;
+
+use num_cpus::get_physical;
+
+use crate::integrator::{IntegratorKind, PathDepths};
+
+/// How an image is rendered, independent of what is in it.
+#[derive(Clone, Copy, Debug)]
+pub struct RenderSettings {
+    pub width: u32,
+    pub height: u32,
+    pub samples_per_pixel: u32,
+    pub max_depth: u32,
+    /// Limits on each kind of bounce within `max_depth`, which by default
+    /// leaves them unlimited.
+    pub diffuse_depth: u32,
+    pub specular_depth: u32,
+    pub transmission_depth: u32,
+    /// Bounces after which paths carrying little light may be ended early by
+    /// Russian roulette.
+    pub roulette_depth: u32,
+    /// Number of render threads, which defaults to the number of physical cores.
+    /// Changing it doesn't change the image.
+    pub threads: usize,
+    pub seed: u64,
+    /// How the colour along each camera ray is worked out.
+    pub integrator: IntegratorKind,
+    /// Trace a few wavelengths per path instead of RGB, so that dispersive
+    /// glass splits light into colours.
+    pub spectral: bool,
+    /// Keep refining the image until this much time has been spent on it.
+    pub time_limit: Option<Duration>,
+    /// Keep refining the image until its estimated mean relative error drops
+    /// to this level.
+    pub target_error: Option<f64>,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            width: 1200,
+            height: 800,
+            samples_per_pixel: 20,
+            max_depth: 50,
+            diffuse_depth: u32::MAX,
+            specular_depth: u32::MAX,
+            transmission_depth: u32::MAX,
+            roulette_depth: 5,
+            threads: get_physical(),
+            seed: 0,
+            integrator: IntegratorKind::Path,
+            spectral: false,
+            time_limit: None,
+            target_error: None,
+        }
+    }
+}
+
+impl RenderSettings {
+    /// Whether the render stops on a time or quality budget rather than
+    /// after `samples_per_pixel` samples.
+    pub fn is_adaptive(&self) -> bool {
+        self.time_limit.is_some() || self.target_error.is_some()
+    }
+
+    pub fn path_depths(&self) -> PathDepths {
+        PathDepths {
+            max: self.max_depth,
+            diffuse: self.diffuse_depth,
+            specular: self.specular_depth,
+            transmission: self.transmission_depth,
+            roulette: self.roulette_depth,
+        }
+    }
+
+    pub fn aspect_ratio(&self) -> f64 {
+        self.width as f64 / self.height.max(1) as f64
+    }
+}
@@ -0,0 +1,242 @@
+use std::sync::OnceLock;
+
+use crate::prelude::*;
+
+pub const LAMBDA_MIN: f64 = 380.;
+pub const LAMBDA_MAX: f64 = 720.;
+const SAMPLES: usize = 4;
+
+/// The wavelengths (in nm) carried by one path. The first is the hero
+/// wavelength; the rest are spread evenly across the visible range from it.
+#[derive(Clone, Copy, Debug)]
+pub struct Wavelengths {
+    lambda: [f64; SAMPLES],
+    pdf: [f64; SAMPLES],
+}
+
+impl Wavelengths {
+    /// Picks the hero wavelength from a uniform random number in [0, 1).
+    pub fn sample(u: f64) -> Self {
+        let range = LAMBDA_MAX - LAMBDA_MIN;
+        let hero = u * range;
+        let mut lambda = [0.; SAMPLES];
+        for (index, wavelength) in lambda.iter_mut().enumerate() {
+            let offset = (hero + index as f64 * range / SAMPLES as f64) % range;
+            *wavelength = LAMBDA_MIN + offset;
+        }
+        Self {
+            lambda,
+            pdf: [1. / range; SAMPLES],
+        }
+    }
+
+    pub fn hero(&self) -> f64 {
+        self.lambda[0]
+    }
+
+    /// Once a path has taken a direction that depends on the wavelength, only
+    /// the hero can follow it. Terminating them again changes nothing.
+    pub fn terminate_secondary(&self) -> Self {
+        if self.pdf[1..].iter().all(|pdf| *pdf == 0.) {
+            return *self;
+        }
+        let mut terminated = *self;
+        terminated.pdf = [0.; SAMPLES];
+        terminated.pdf[0] = self.pdf[0] / SAMPLES as f64;
+        terminated
+    }
+
+    /// Converts the radiance carried at these wavelengths to linear RGB, white
+    /// balanced so that a flat spectrum comes back as grey.
+    pub fn to_rgb(&self, spectrum: &Spectrum) -> Colour {
+        let mut xyz = Vector3::default();
+        for index in 0..SAMPLES {
+            if self.pdf[index] > 0. {
+                xyz += spectrum.0[index] * cie_xyz(self.lambda[index]) / self.pdf[index];
+            }
+        }
+        let rgb = xyz_to_rgb(&(xyz / SAMPLES as f64));
+        let white = white_rgb();
+        Colour::new(
+            rgb.x() / white.x(),
+            rgb.y() / white.y(),
+            rgb.z() / white.z(),
+        )
+    }
+}
+
+/// Values of a spectral quantity at each of a path's wavelengths.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Spectrum([f64; SAMPLES]);
+
+impl Spectrum {
+    /// Upsamples an RGB colour to a smooth spectrum (Smits 1999) and evaluates
+    /// it at each of the wavelengths.
+    pub fn from_rgb(colour: &Colour, wavelengths: &Wavelengths) -> Self {
+        let mut values = [0.; SAMPLES];
+        for (value, lambda) in values.iter_mut().zip(wavelengths.lambda.iter()) {
+            *value = smits(colour, *lambda);
+        }
+        Self(values)
+    }
+
+    /// The same value at every wavelength.
+    pub fn splat(value: f64) -> Self {
+        Self([value; SAMPLES])
+    }
+
+    pub fn max_value(&self) -> f64 {
+        self.0.iter().copied().fold(f64::MIN, f64::max)
+    }
+}
+
+impl_op_ex!(+|lhs: &Spectrum, rhs: &Spectrum| -> Spectrum {
+    let mut values = lhs.0;
+    for (value, other) in values.iter_mut().zip(rhs.0.iter()) {
+        *value += other;
+    }
+    Spectrum(values)
+});
+
+impl_op_ex!(*|lhs: &Spectrum, rhs: &Spectrum| -> Spectrum {
+    let mut values = lhs.0;
+    for (value, other) in values.iter_mut().zip(rhs.0.iter()) {
+        *value *= other;
+    }
+    Spectrum(values)
+});
+
+impl_op_ex!(/|lhs: &Spectrum, rhs: f64| -> Spectrum {
+    let mut values = lhs.0;
+    for value in values.iter_mut() {
+        *value /= rhs;
+    }
+    Spectrum(values)
+});
+
+const SMITS_BINS: usize = 10;
+const SMITS_WHITE: [f64; SMITS_BINS] = [
+    1.0000, 1.0000, 0.9999, 0.9993, 0.9992, 0.9998, 1.0000, 1.0000, 1.0000, 1.0000,
+];
+const SMITS_CYAN: [f64; SMITS_BINS] = [
+    0.9710, 0.9426, 1.0007, 1.0007, 1.0007, 1.0007, 0.1564, 0.0000, 0.0000, 0.0000,
+];
+const SMITS_MAGENTA: [f64; SMITS_BINS] = [
+    1.0000, 1.0000, 0.9685, 0.2229, 0.0000, 0.0458, 0.8369, 1.0000, 1.0000, 0.9959,
+];
+const SMITS_YELLOW: [f64; SMITS_BINS] = [
+    0.0001, 0.0000, 0.1088, 0.6651, 1.0000, 1.0000, 0.9996, 0.9586, 0.9685, 0.9840,
+];
+const SMITS_RED: [f64; SMITS_BINS] = [
+    0.1012, 0.0515, 0.0000, 0.0000, 0.0000, 0.0000, 0.8325, 1.0149, 1.0149, 1.0149,
+];
+const SMITS_GREEN: [f64; SMITS_BINS] = [
+    0.0000, 0.0000, 0.0273, 0.7937, 1.0000, 0.9418, 0.1719, 0.0000, 0.0000, 0.0025,
+];
+const SMITS_BLUE: [f64; SMITS_BINS] = [
+    1.0000, 1.0000, 0.8916, 0.3323, 0.0000, 0.0000, 0.0003, 0.0369, 0.0483, 0.0496,
+];
+
+fn smits(colour: &Colour, lambda: f64) -> f64 {
+    let bin = (((lambda - LAMBDA_MIN) / (LAMBDA_MAX - LAMBDA_MIN) * SMITS_BINS as f64) as usize)
+        .min(SMITS_BINS - 1);
+    let (r, g, b) = (colour.x(), colour.y(), colour.z());
+
+    if r <= g && r <= b {
+        r * SMITS_WHITE[bin]
+            + match g <= b {
+                true => (g - r) * SMITS_CYAN[bin] + (b - g) * SMITS_BLUE[bin],
+                false => (b - r) * SMITS_CYAN[bin] + (g - b) * SMITS_GREEN[bin],
+            }
+    } else if g <= r && g <= b {
+        g * SMITS_WHITE[bin]
+            + match r <= b {
+                true => (r - g) * SMITS_MAGENTA[bin] + (b - r) * SMITS_BLUE[bin],
+                false => (b - g) * SMITS_MAGENTA[bin] + (r - b) * SMITS_RED[bin],
+            }
+    } else {
+        b * SMITS_WHITE[bin]
+            + match r <= g {
+                true => (r - b) * SMITS_YELLOW[bin] + (g - r) * SMITS_GREEN[bin],
+                false => (g - b) * SMITS_YELLOW[bin] + (r - g) * SMITS_RED[bin],
+            }
+    }
+}
+
+/// The CIE 1931 colour matching functions, using the multi-lobe Gaussian fit
+/// of Wyman, Sloan and Shirley (2013).
+fn cie_xyz(lambda: f64) -> Vector3 {
+    let g = |mu: f64, sigma_low: f64, sigma_high: f64| {
+        let sigma = match lambda < mu {
+            true => sigma_low,
+            false => sigma_high,
+        };
+        (-0.5 * ((lambda - mu) / sigma).powi(2)).exp()
+    };
+    Vector3::new(
+        1.056 * g(599.8, 37.9, 31.0) + 0.362 * g(442.0, 16.0, 26.7) - 0.065 * g(501.1, 20.4, 26.2),
+        0.821 * g(568.8, 46.9, 40.5) + 0.286 * g(530.9, 16.3, 31.1),
+        1.217 * g(437.0, 11.8, 36.0) + 0.681 * g(459.0, 26.0, 13.8),
+    )
+}
+
+/// XYZ to linear sRGB.
+pub fn xyz_to_rgb(xyz: &Vector3) -> Colour {
+    let (x, y, z) = (xyz.x(), xyz.y(), xyz.z());
+    Colour::new(
+        3.2406 * x - 1.5372 * y - 0.4986 * z,
+        -0.9689 * x + 1.8758 * y + 0.0415 * z,
+        0.0557 * x - 0.2040 * y + 1.0570 * z,
+    )
+}
+
+/// The RGB of a flat spectrum of 1 over the visible range.
+fn white_rgb() -> Colour {
+    static WHITE: OnceLock<Colour> = OnceLock::new();
+    *WHITE.get_or_init(|| {
+        let steps = 1000;
+        let step = (LAMBDA_MAX - LAMBDA_MIN) / steps as f64;
+        let mut xyz = Vector3::default();
+        for index in 0..steps {
+            xyz += cie_xyz(LAMBDA_MIN + (index as f64 + 0.5) * step) * step;
+        }
+        xyz_to_rgb(&xyz)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Averages the colour of a flat spectrum over evenly spread hero
+    /// wavelengths, after `prisms` dispersive interfaces.
+    fn mean_flat_colour(prisms: usize) -> Colour {
+        let steps = 1000;
+        let mut total = Colour::default();
+        for index in 0..steps {
+            let mut wavelengths = Wavelengths::sample((index as f64 + 0.5) / steps as f64);
+            for _ in 0..prisms {
+                wavelengths = wavelengths.terminate_secondary();
+            }
+            total += wavelengths.to_rgb(&Spectrum::splat(1.));
+        }
+        total / steps as f64
+    }
+
+    fn assert_grey(colour: Colour) {
+        for channel in [colour.x(), colour.y(), colour.z()] {
+            assert!((channel - 1.).abs() < 1e-3, "{:?} is not grey", colour);
+        }
+    }
+
+    #[test]
+    fn flat_spectrum_comes_back_grey() {
+        assert_grey(mean_flat_colour(0));
+    }
+
+    #[test]
+    fn dispersion_keeps_the_energy_of_a_path() {
+        assert_grey(mean_flat_colour(1));
+        assert_grey(mean_flat_colour(2));
+    }
+}
@@ -1,8 +1,13 @@
+use crate::bvh::Aabb;
+use crate::light_tree::Emitter;
 use crate::prelude::*;
+use crate::stats::{count, Counter};
 
 use num_traits::NumCast;
+use std::f64::consts::PI;
 use std::sync::Arc;
 
+#[derive(Debug)]
 pub struct Sphere {
     center: Point3,
     radius: f64,
@@ -19,10 +24,36 @@ impl Sphere {
     }
 }
 
+impl Sphere {
+    /// Maps a point on the unit sphere to longitude and latitude, with v
+    /// running from the south pole to the north.
+    fn uv(p: &Point3) -> (f64, f64) {
+        let theta = (-p.y()).clamp(-1., 1.).acos();
+        let phi = (-p.z()).atan2(p.x()) + PI;
+        (phi / (2. * PI), theta / PI)
+    }
+
+    /// The partial derivatives of the hit point along u and v, for the
+    /// mapping in `uv`.
+    fn derivatives(&self, n: &Vector3) -> (Vector3, Vector3) {
+        let sin_theta = (n.x() * n.x() + n.z() * n.z()).sqrt().max(1e-9);
+        let dpdu = 2. * PI * self.radius * Vector3::new(n.z(), 0, -n.x());
+        let dpdv = PI
+            * self.radius
+            * Vector3::new(
+                -n.x() * n.y() / sin_theta,
+                sin_theta,
+                -n.y() * n.z() / sin_theta,
+            );
+        (dpdu, dpdv)
+    }
+}
+
 impl Boxable for Sphere {}
 
 impl Hittable for Sphere {
     fn hit(&self, ray: &Ray, t_min: f64, t_max: f64, rec: &mut Hit) -> bool {
+        count(Counter::IntersectionTests);
         let oc = ray.origin() - self.center;
         let a = ray.direction().length_squared();
         let half_b = ray.direction().dot(&oc);
@@ -47,10 +78,28 @@ impl Hittable for Sphere {
             rec.p = ray.at(rec.t);
             let outward_normal = (rec.p - self.center) / self.radius;
             rec.set_face_normal(ray, &outward_normal);
-            if let Some(material) = &self.material {
-                rec.material = Some(material.clone())
-            }
+            (rec.u, rec.v) = Sphere::uv(&outward_normal);
+            (rec.dpdu, rec.dpdv) = self.derivatives(&outward_normal);
+            rec.material = self.material.clone();
             true
         }
     }
+
+    fn material(&self) -> Option<&Arc<dyn Material>> {
+        self.material.as_ref()
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let radius = self.radius.abs();
+        let extent = Vector3::new(radius, radius, radius);
+        Some(Aabb::new(self.center - extent, self.center + extent))
+    }
+
+    fn emitter(&self) -> Option<Emitter> {
+        let material = self.material.as_ref()?;
+        match material.average_emission().luminance() > 0. {
+            true => Some(Emitter::new(self.center, self.radius, material.clone())),
+            false => None,
+        }
+    }
 }
@@ -0,0 +1,207 @@
+use std::cell::Cell;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy)]
+pub enum Counter {
+    CameraRays,
+    BounceRays,
+    ShadowRays,
+    IntersectionTests,
+    BvhNodeVisits,
+    DepthTerminations,
+    RouletteTerminations,
+}
+
+const COUNTERS: usize = 7;
+const COUNTER_NAMES: [&str; COUNTERS] = [
+    "Camera rays",
+    "Bounce rays",
+    "Shadow rays",
+    "Intersection tests",
+    "BVH node visits",
+    "Paths terminated by depth",
+    "Paths terminated by roulette",
+];
+
+thread_local! {
+    static LOCAL_COUNTS: [Cell<u64>; COUNTERS] = Default::default();
+}
+
+/// Counts an event on the current thread; the totals are only visible once the
+/// thread calls `Stats::flush`.
+pub fn count(counter: Counter) {
+    LOCAL_COUNTS.with(|counts| {
+        let count = &counts[counter as usize];
+        count.set(count.get() + 1);
+    });
+}
+
+/// The current thread's count of an event since it last flushed, for
+/// measuring the work done by a single ray.
+pub fn local_count(counter: Counter) -> u64 {
+    LOCAL_COUNTS.with(|counts| counts[counter as usize].get())
+}
+
+/// What a job's progress is measured against.
+#[derive(Clone, Copy)]
+pub enum Budget {
+    /// A fixed number of pixel updates.
+    Pixels(u64),
+    /// A fixed amount of render time.
+    Time(Duration),
+    /// Runs until a quality target is met, so progress can't be predicted.
+    OpenEnded,
+}
+
+pub struct Stats {
+    counts: [AtomicU64; COUNTERS],
+    pixels_done: AtomicU64,
+    budget: Budget,
+    image_pixels: u64,
+    started: Instant,
+    paused_since: Mutex<Option<Instant>>,
+    paused_for: AtomicU64,
+    finished_after: AtomicU64,
+}
+
+impl Stats {
+    pub fn new(budget: Budget, image_pixels: u64) -> Self {
+        Self {
+            counts: Default::default(),
+            pixels_done: AtomicU64::new(0),
+            budget,
+            image_pixels,
+            started: Instant::now(),
+            paused_since: Mutex::new(None),
+            paused_for: AtomicU64::new(0),
+            finished_after: AtomicU64::new(0),
+        }
+    }
+
+    /// Moves the current thread's counts into the shared totals.
+    pub fn flush(&self, pixels_done: u64) {
+        LOCAL_COUNTS.with(|counts| {
+            for (total, count) in self.counts.iter().zip(counts.iter()) {
+                total.fetch_add(count.replace(0), Ordering::Relaxed);
+            }
+        });
+        self.pixels_done.fetch_add(pixels_done, Ordering::Relaxed);
+    }
+
+    /// Stops the clock; later calls keep the first finishing time.
+    pub fn finish(&self) {
+        let elapsed = self.elapsed().as_nanos().max(1) as u64;
+        let _ =
+            self.finished_after
+                .compare_exchange(0, elapsed, Ordering::Relaxed, Ordering::Relaxed);
+    }
+
+    pub fn pause(&self) {
+        self.paused_since
+            .lock()
+            .expect("Something went wrong inside the stats lock")
+            .get_or_insert_with(Instant::now);
+    }
+
+    pub fn resume(&self) {
+        let paused_since = self
+            .paused_since
+            .lock()
+            .expect("Something went wrong inside the stats lock")
+            .take();
+        if let Some(paused_since) = paused_since {
+            self.paused_for
+                .fetch_add(paused_since.elapsed().as_nanos() as u64, Ordering::Relaxed);
+        }
+    }
+
+    pub fn get(&self, counter: Counter) -> u64 {
+        self.counts[counter as usize].load(Ordering::Relaxed)
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        match self.finished_after.load(Ordering::Relaxed) {
+            0 => {
+                let paused_since = *self
+                    .paused_since
+                    .lock()
+                    .expect("Something went wrong inside the stats lock");
+                let now = paused_since.unwrap_or_else(Instant::now);
+                (now - self.started).saturating_sub(Duration::from_nanos(
+                    self.paused_for.load(Ordering::Relaxed),
+                ))
+            }
+            nanos => Duration::from_nanos(nanos),
+        }
+    }
+
+    pub fn progress(&self) -> Option<f64> {
+        match self.budget {
+            Budget::Pixels(pixels_total) => {
+                Some(self.pixels_done.load(Ordering::Relaxed) as f64 / pixels_total.max(1) as f64)
+            }
+            Budget::Time(limit) => {
+                Some((self.elapsed().as_secs_f64() / limit.as_secs_f64().max(f64::EPSILON)).min(1.))
+            }
+            Budget::OpenEnded => None,
+        }
+    }
+
+    pub fn eta(&self) -> Option<Duration> {
+        match (self.budget, self.progress()) {
+            (Budget::Time(limit), _) => Some(limit.saturating_sub(self.elapsed())),
+            (_, Some(progress)) if progress > 0. => {
+                Some(self.elapsed().mul_f64((1. - progress) / progress))
+            }
+            _ => None,
+        }
+    }
+
+    /// Camera rays traced per pixel of the image so far.
+    pub fn samples_per_pixel(&self) -> f64 {
+        self.get(Counter::CameraRays) as f64 / self.image_pixels.max(1) as f64
+    }
+
+    pub fn rays_per_second(&self) -> f64 {
+        let rays = self.get(Counter::CameraRays)
+            + self.get(Counter::BounceRays)
+            + self.get(Counter::ShadowRays);
+        rays as f64 / self.elapsed().as_secs_f64().max(f64::EPSILON)
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = vec![
+            format!(
+                "Elapsed {:.1}s, {} done, ETA {}",
+                self.elapsed().as_secs_f64(),
+                self.progress()
+                    .map(|progress| format!("{:.0}%", progress * 100.))
+                    .unwrap_or_else(|| "?".into()),
+                self.eta()
+                    .map(|eta| format!("{:.1}s", eta.as_secs_f64()))
+                    .unwrap_or_else(|| "unknown".into()),
+            ),
+            format!("Samples per pixel: {:.1}", self.samples_per_pixel()),
+        ];
+        lines.extend(
+            COUNTER_NAMES
+                .iter()
+                .zip(self.counts.iter())
+                .map(|(name, count)| format!("{}: {}", name, count.load(Ordering::Relaxed))),
+        );
+        lines.push(format!(
+            "Rays per second: {:.3}M",
+            self.rays_per_second() / 1e6
+        ));
+        lines
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.lines().join("\n"))
+    }
+}
@@ -0,0 +1,421 @@
+use std::fmt::Debug;
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::prelude::*;
+
+use rand::prelude::*;
+
+/// A colour or value that varies across a surface.
+pub trait Texture: Send + Sync + Debug {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Colour;
+
+    /// Reads the texture as a single value, so greyscale maps can drive
+    /// scalar parameters.
+    fn scalar(&self, u: f64, v: f64, p: &Point3) -> f64 {
+        self.value(u, v, p).luminance()
+    }
+}
+
+/// Anything that can be used where a material takes a texture.
+pub trait IntoTexture {
+    fn into_texture(self) -> Arc<dyn Texture>;
+}
+
+impl IntoTexture for Arc<dyn Texture> {
+    fn into_texture(self) -> Arc<dyn Texture> {
+        self
+    }
+}
+
+impl<T: Texture + 'static> IntoTexture for T {
+    fn into_texture(self) -> Arc<dyn Texture> {
+        Arc::new(self)
+    }
+}
+
+impl IntoTexture for Colour {
+    fn into_texture(self) -> Arc<dyn Texture> {
+        Arc::new(SolidColour::new(self))
+    }
+}
+
+impl IntoTexture for f64 {
+    fn into_texture(self) -> Arc<dyn Texture> {
+        Arc::new(SolidColour::new(Colour::new(self, self, self)))
+    }
+}
+
+#[derive(Debug)]
+pub struct SolidColour {
+    colour: Colour,
+}
+
+impl SolidColour {
+    pub fn new(colour: Colour) -> Self {
+        Self { colour }
+    }
+}
+
+impl Texture for SolidColour {
+    fn value(&self, _u: f64, _v: f64, _p: &Point3) -> Colour {
+        self.colour
+    }
+}
+
+/// A 3D checkerboard, so it wraps around curved surfaces without distortion.
+#[derive(Debug)]
+pub struct Checker {
+    odd: Arc<dyn Texture>,
+    even: Arc<dyn Texture>,
+    scale: f64,
+}
+
+impl Checker {
+    pub fn new(odd: impl IntoTexture, even: impl IntoTexture, scale: f64) -> Self {
+        Self {
+            odd: odd.into_texture(),
+            even: even.into_texture(),
+            scale,
+        }
+    }
+}
+
+impl Texture for Checker {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Colour {
+        let sines =
+            (self.scale * p.x()).sin() * (self.scale * p.y()).sin() * (self.scale * p.z()).sin();
+        match sines < 0. {
+            true => self.odd.value(u, v, p),
+            false => self.even.value(u, v, p),
+        }
+    }
+}
+
+const PERLIN_POINTS: usize = 256;
+
+/// Perlin gradient noise summed over several octaves, giving values between
+/// 0 and 1 that suit marble-like colours or bumpy surfaces.
+#[derive(Debug)]
+pub struct Noise {
+    gradients: Vec<Vector3>,
+    permutations: [Vec<usize>; 3],
+    scale: f64,
+    octaves: u32,
+}
+
+impl Noise {
+    pub fn new(scale: f64, octaves: u32) -> Self {
+        // A fixed seed keeps scenes identical between runs.
+        let mut rng = StdRng::seed_from_u64(0x5eed);
+        let gradients = (0..PERLIN_POINTS)
+            .map(|_| Vector3::random_range_from_rng(&mut rng, -1., 1.).unit_vector())
+            .collect();
+        let mut permutation = || {
+            let mut points: Vec<usize> = (0..PERLIN_POINTS).collect();
+            points.shuffle(&mut rng);
+            points
+        };
+        Self {
+            gradients,
+            permutations: [permutation(), permutation(), permutation()],
+            scale,
+            octaves: octaves.max(1),
+        }
+    }
+
+    /// Smoothly interpolated noise in roughly [-1, 1].
+    fn noise(&self, p: &Point3) -> f64 {
+        let cell = [p.x().floor(), p.y().floor(), p.z().floor()];
+        let offset = [p.x() - cell[0], p.y() - cell[1], p.z() - cell[2]];
+        let smooth = offset.map(|f| f * f * (3. - 2. * f));
+
+        let mut sum = 0.;
+        for corner in 0..8 {
+            let step = [corner & 1, (corner >> 1) & 1, (corner >> 2) & 1];
+            let mut hash = 0;
+            let mut weight = 1.;
+            for axis in 0..3 {
+                let index = (cell[axis] as i64 + step[axis] as i64) & (PERLIN_POINTS as i64 - 1);
+                hash ^= self.permutations[axis][index as usize];
+                weight *= match step[axis] {
+                    1 => smooth[axis],
+                    _ => 1. - smooth[axis],
+                };
+            }
+            let to_point = Vector3::new(
+                offset[0] - step[0] as f64,
+                offset[1] - step[1] as f64,
+                offset[2] - step[2] as f64,
+            );
+            sum += weight * self.gradients[hash].dot(&to_point);
+        }
+        sum
+    }
+}
+
+impl Texture for Noise {
+    fn value(&self, _u: f64, _v: f64, p: &Point3) -> Colour {
+        let mut sum = 0.;
+        let mut amplitude = 1.;
+        let mut total_amplitude = 0.;
+        let mut point = self.scale * p;
+        for _ in 0..self.octaves {
+            sum += amplitude * self.noise(&point);
+            total_amplitude += amplitude;
+            amplitude *= 0.5;
+            point *= 2.;
+        }
+        let value = 0.5 * (1. + sum / total_amplitude);
+        Colour::new(value, value, value)
+    }
+}
+
+/// A texture loaded from a PPM file (P3 or P6) and mapped by the surface's
+/// UV coordinates.
+#[derive(Debug)]
+pub struct ImageTexture {
+    width: usize,
+    height: usize,
+    pixels: Vec<Colour>,
+}
+
+impl ImageTexture {
+    /// Loads a colour image, undoing the gamma the renderer writes images with.
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut texture = Self::open_raw(path)?;
+        for pixel in texture.pixels.iter_mut() {
+            *pixel = *pixel * *pixel;
+        }
+        Ok(texture)
+    }
+
+    /// Loads an image as-is, for maps holding values rather than colours.
+    pub fn open_raw(path: &Path) -> Result<Self> {
+        let bytes = fs::read(path)?;
+        let invalid = |message: &str| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), message),
+            )
+        };
+
+        // The header is four whitespace separated fields, which may have
+        // comments between them.
+        let mut fields = vec![];
+        let mut position = 0;
+        while fields.len() < 4 {
+            while position < bytes.len() && bytes[position].is_ascii_whitespace() {
+                position += 1;
+            }
+            if position < bytes.len() && bytes[position] == b'#' {
+                while position < bytes.len() && bytes[position] != b'\n' {
+                    position += 1;
+                }
+                continue;
+            }
+            let start = position;
+            while position < bytes.len() && !bytes[position].is_ascii_whitespace() {
+                position += 1;
+            }
+            if start == position {
+                return Err(invalid("truncated header"));
+            }
+            fields.push(String::from_utf8_lossy(&bytes[start..position]).to_string());
+        }
+        let number = |field: &str| {
+            field
+                .parse::<usize>()
+                .map_err(|_| invalid("invalid header"))
+        };
+        let (width, height, max_value) = (
+            number(&fields[1])?,
+            number(&fields[2])?,
+            number(&fields[3])?,
+        );
+        if max_value == 0 || max_value > 255 {
+            return Err(invalid("only 8-bit images are supported"));
+        }
+
+        let samples: Vec<u8> = match fields[0].as_str() {
+            "P6" => bytes.get(position + 1..).unwrap_or_default().to_vec(),
+            "P3" => String::from_utf8_lossy(&bytes[position..])
+                .split_ascii_whitespace()
+                .map(|sample| {
+                    sample
+                        .parse::<u8>()
+                        .map_err(|_| invalid("invalid pixel data"))
+                })
+                .collect::<Result<_>>()?,
+            _ => return Err(invalid("not a PPM image")),
+        };
+        if samples.len() < width * height * 3 {
+            return Err(invalid("truncated pixel data"));
+        }
+
+        let pixels = samples
+            .chunks_exact(3)
+            .take(width * height)
+            .map(|rgb| Colour::new(rgb[0], rgb[1], rgb[2]) / max_value as f64)
+            .collect();
+        Ok(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+}
+
+impl ImageTexture {
+    /// An image from linear pixels listed row by row from the top.
+    pub fn from_pixels(width: usize, height: usize, pixels: Vec<Colour>) -> Self {
+        assert_eq!(pixels.len(), width * height, "Wrong number of pixels");
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Loads a Radiance RGBE (.hdr) image, as used for HDR environment maps.
+    /// Its values are linear and may be far brighter than 1.
+    pub fn open_hdr(path: &Path) -> Result<Self> {
+        let bytes = fs::read(path)?;
+        let invalid = |message: &str| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), message),
+            )
+        };
+
+        let mut lines: Vec<String> = vec![];
+        let mut position = 0;
+        // The header ends with a blank line, followed by the resolution.
+        while lines.len() < 2 || !lines[lines.len() - 2].is_empty() {
+            let end = bytes[position..]
+                .iter()
+                .position(|&byte| byte == b'\n')
+                .ok_or_else(|| invalid("truncated header"))?;
+            lines.push(String::from_utf8_lossy(&bytes[position..position + end]).to_string());
+            position += end + 1;
+        }
+        if !lines[0].starts_with("#?") {
+            return Err(invalid("not a Radiance HDR image"));
+        }
+        if lines
+            .iter()
+            .any(|line| line.starts_with("FORMAT=") && line != "FORMAT=32-bit_rle_rgbe")
+        {
+            return Err(invalid("only RGBE images are supported"));
+        }
+        let resolution: Vec<&str> = lines[lines.len() - 1].split_whitespace().collect();
+        let (height, width) = match resolution.as_slice() {
+            ["-Y", height, "+X", width] => (
+                height
+                    .parse::<usize>()
+                    .map_err(|_| invalid("invalid size"))?,
+                width
+                    .parse::<usize>()
+                    .map_err(|_| invalid("invalid size"))?,
+            ),
+            _ => {
+                return Err(invalid(
+                    "only top to bottom, left to right images are supported",
+                ))
+            }
+        };
+
+        let mut pixels = Vec::with_capacity(width * height);
+        let mut scanline = vec![0u8; width * 4];
+        for _ in 0..height {
+            position = Self::read_scanline(&bytes, position, &mut scanline)
+                .ok_or_else(|| invalid("truncated pixel data"))?;
+            pixels.extend(scanline.chunks_exact(4).map(|rgbe| match rgbe[3] {
+                0 => Colour::default(),
+                exponent => {
+                    let scale = 2f64.powi(exponent as i32 - 136);
+                    Colour::new(
+                        (rgbe[0] as f64 + 0.5) * scale,
+                        (rgbe[1] as f64 + 0.5) * scale,
+                        (rgbe[2] as f64 + 0.5) * scale,
+                    )
+                }
+            }));
+        }
+        Ok(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    /// Reads one row of RGBE pixels, either run-length encoded a channel at a
+    /// time or stored flat. Returns the position after the row.
+    fn read_scanline(bytes: &[u8], mut position: usize, scanline: &mut [u8]) -> Option<usize> {
+        let width = scanline.len() / 4;
+        let header = bytes.get(position..position + 4)?;
+        let run_length_encoded = (8..0x8000).contains(&width)
+            && header[0] == 2
+            && header[1] == 2
+            && ((header[2] as usize) << 8 | header[3] as usize) == width;
+        if !run_length_encoded {
+            scanline.copy_from_slice(bytes.get(position..position + scanline.len())?);
+            return Some(position + scanline.len());
+        }
+
+        position += 4;
+        for channel in 0..4 {
+            let mut x = 0;
+            while x < width {
+                let count = *bytes.get(position)? as usize;
+                position += 1;
+                if count == 0 || count == 128 {
+                    return None;
+                }
+                match count > 128 {
+                    true => {
+                        let value = *bytes.get(position)?;
+                        position += 1;
+                        for _ in 0..(count - 128).min(width - x) {
+                            scanline[x * 4 + channel] = value;
+                            x += 1;
+                        }
+                    }
+                    false => {
+                        let values = bytes.get(position..position + count)?;
+                        position += count;
+                        for &value in values.iter().take(width - x) {
+                            scanline[x * 4 + channel] = value;
+                            x += 1;
+                        }
+                    }
+                }
+            }
+        }
+        Some(position)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The pixel in column `x` of row `y`, counting rows from the top.
+    pub fn pixel(&self, x: usize, y: usize) -> Colour {
+        self.pixels[y * self.width + x]
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _p: &Point3) -> Colour {
+        // Image rows run top to bottom, while v runs bottom to top.
+        let x = ((u.clamp(0., 1.) * self.width as f64) as usize).min(self.width - 1);
+        let y = (((1. - v.clamp(0., 1.)) * self.height as f64) as usize).min(self.height - 1);
+        self.pixels[y * self.width + x]
+    }
+}
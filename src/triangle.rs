@@ -0,0 +1,158 @@
+use std::sync::Arc;
+
+use crate::bvh::Aabb;
+use crate::prelude::*;
+use crate::stats::{count, Counter};
+use crate::texture::IntoTexture;
+
+#[derive(Debug)]
+pub struct Triangle {
+    vertices: [Point3; 3],
+    uvs: [(f64, f64); 3],
+    material: Arc<dyn Material>,
+}
+
+impl Triangle {
+    pub fn new(a: Point3, b: Point3, c: Point3, material: Arc<dyn Material>) -> Self {
+        Self {
+            vertices: [a, b, c],
+            uvs: [(0., 0.), (1., 0.), (0., 1.)],
+            material,
+        }
+    }
+
+    /// Texture coordinates for each vertex, which default to a corner of the
+    /// unit square.
+    pub fn with_uvs(mut self, uvs: [(f64, f64); 3]) -> Self {
+        self.uvs = uvs;
+        self
+    }
+
+    /// Two triangles covering the parallelogram with a corner at `origin`
+    /// and sides `u` and `v`, mapped to the whole unit square.
+    pub fn quad(
+        origin: Point3,
+        u: Vector3,
+        v: Vector3,
+        material: Arc<dyn Material>,
+    ) -> [Triangle; 2] {
+        [
+            Triangle::new(origin, origin + u, origin + u + v, material.clone()).with_uvs([
+                (0., 0.),
+                (1., 0.),
+                (1., 1.),
+            ]),
+            Triangle::new(origin, origin + u + v, origin + v, material).with_uvs([
+                (0., 0.),
+                (1., 1.),
+                (0., 1.),
+            ]),
+        ]
+    }
+
+    /// Displacement mapping: tessellates the parallelogram like `quad` into a
+    /// `resolution` by `resolution` grid of cells, then moves each vertex out
+    /// along the normal (`u` × `v`) by `scale` times the height texture there.
+    /// Unlike bump mapping this changes the silhouette, at the cost of
+    /// 2 × `resolution`² triangles.
+    pub fn displaced(
+        origin: Point3,
+        u: Vector3,
+        v: Vector3,
+        resolution: u32,
+        height: impl IntoTexture,
+        scale: f64,
+        material: Arc<dyn Material>,
+    ) -> Vec<Triangle> {
+        let height = height.into_texture();
+        let normal = u.cross(&v).unit_vector();
+        let resolution = resolution.max(1);
+        let vertex = |i: u32, j: u32| {
+            let (s, t) = (i as f64 / resolution as f64, j as f64 / resolution as f64);
+            let p = origin + s * u + t * v;
+            (p + scale * height.scalar(s, t, &p) * normal, (s, t))
+        };
+
+        let mut triangles = Vec::with_capacity(2 * (resolution * resolution) as usize);
+        for j in 0..resolution {
+            for i in 0..resolution {
+                let (a, a_uv) = vertex(i, j);
+                let (b, b_uv) = vertex(i + 1, j);
+                let (c, c_uv) = vertex(i + 1, j + 1);
+                let (d, d_uv) = vertex(i, j + 1);
+                triangles
+                    .push(Triangle::new(a, b, c, material.clone()).with_uvs([a_uv, b_uv, c_uv]));
+                triangles
+                    .push(Triangle::new(a, c, d, material.clone()).with_uvs([a_uv, c_uv, d_uv]));
+            }
+        }
+        triangles
+    }
+
+    /// Solves for the change in position per unit of u and v across the
+    /// triangle.
+    fn derivatives(&self, edge1: &Vector3, edge2: &Vector3) -> (Vector3, Vector3) {
+        let (du1, dv1) = (self.uvs[1].0 - self.uvs[0].0, self.uvs[1].1 - self.uvs[0].1);
+        let (du2, dv2) = (self.uvs[2].0 - self.uvs[0].0, self.uvs[2].1 - self.uvs[0].1);
+        let determinant = du1 * dv2 - dv1 * du2;
+        match determinant.abs() > 1e-12 {
+            true => (
+                (dv2 * edge1 - dv1 * edge2) / determinant,
+                (du1 * edge2 - du2 * edge1) / determinant,
+            ),
+            false => (*edge1, *edge2),
+        }
+    }
+}
+
+impl Boxable for Triangle {}
+
+impl Hittable for Triangle {
+    // Möller-Trumbore intersection.
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64, rec: &mut Hit) -> bool {
+        count(Counter::IntersectionTests);
+        let [a, b, c] = self.vertices;
+        let edge1 = b - a;
+        let edge2 = c - a;
+        let p = ray.direction().cross(&edge2);
+        let determinant = edge1.dot(&p);
+        if determinant.abs() < 1e-12 {
+            return false;
+        }
+
+        let inverse = 1. / determinant;
+        let s = ray.origin() - a;
+        let beta = s.dot(&p) * inverse;
+        if !(0. ..=1.).contains(&beta) {
+            return false;
+        }
+        let q = s.cross(&edge1);
+        let gamma = ray.direction().dot(&q) * inverse;
+        if gamma < 0. || beta + gamma > 1. {
+            return false;
+        }
+        let t = edge2.dot(&q) * inverse;
+        if t < t_min || t_max < t {
+            return false;
+        }
+
+        let alpha = 1. - beta - gamma;
+        rec.t = t;
+        rec.p = ray.at(t);
+        rec.set_face_normal(ray, &edge1.cross(&edge2).unit_vector());
+        rec.u = alpha * self.uvs[0].0 + beta * self.uvs[1].0 + gamma * self.uvs[2].0;
+        rec.v = alpha * self.uvs[0].1 + beta * self.uvs[1].1 + gamma * self.uvs[2].1;
+        (rec.dpdu, rec.dpdv) = self.derivatives(&edge1, &edge2);
+        rec.material = Some(self.material.clone());
+        true
+    }
+
+    fn material(&self) -> Option<&Arc<dyn Material>> {
+        Some(&self.material)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let [a, b, c] = self.vertices;
+        Some(Aabb::new(a, b).union(&Aabb::new(a, c)))
+    }
+}
@@ -1,5 +1,6 @@
+use std::fmt;
+
 use ::rand::prelude::*;
-use macroquad::prelude::Color;
 use num_traits::NumCast;
 
 #[derive(Clone, Copy, Debug)]
@@ -65,13 +66,19 @@ impl Vector3 {
         *self / self.length()
     }
 
-    pub fn to_color(self, samples_per_pixel: u32) -> Color {
-        let scale = 1. / samples_per_pixel as f64;
-        Color::from_rgba(
-            (255.999 * (self.x * scale).sqrt()) as u8,
-            (255.999 * (self.y * scale).sqrt()) as u8,
-            (255.999 * (self.z * scale).sqrt()) as u8,
-            255,
+    pub fn max_component(&self) -> f64 {
+        self.x.max(self.y).max(self.z)
+    }
+
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.x + 0.7152 * self.y + 0.0722 * self.z
+    }
+
+    pub fn to_rgb8(self) -> (u8, u8, u8) {
+        (
+            (255.999 * self.x.sqrt()) as u8,
+            (255.999 * self.y.sqrt()) as u8,
+            (255.999 * self.z.sqrt()) as u8,
         )
     }
 
@@ -137,6 +144,11 @@ impl Vector3 {
         }
     }
 
+    /// Raises e to each component.
+    pub fn exp(&self) -> Self {
+        Self::new(self.x.exp(), self.y.exp(), self.z.exp())
+    }
+
     pub fn near_zero(&self) -> bool {
         // Return true if the vector is close to zero in all dimensions.
         const S: f64 = 1e-8;
@@ -155,6 +167,12 @@ impl Vector3 {
     }
 }
 
+impl fmt::Display for Vector3 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({:.3}, {:.3}, {:.3})", self.x, self.y, self.z)
+    }
+}
+
 // Below are the operator definitions
 
 impl_op_ex!(+|lhs: &Vector3, rhs: &Vector3| -> Vector3 {
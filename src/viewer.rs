@@ -0,0 +1,263 @@
+use std::sync::{Arc, Mutex};
+
+use crate::camera::CameraSettings;
+use crate::canvas::Canvas;
+use crate::denoiser::Denoiser;
+use crate::framebuffer::FrameBuffer;
+use crate::integrator::INTEGRATORS;
+use crate::job::{JobKind, JobState, RenderJob};
+use crate::navigation::Navigator;
+use crate::options::Options;
+use crate::picking::{pick, Pick};
+use crate::scene::*;
+use crate::vector3::Colour;
+
+use macroquad::prelude::*;
+
+/// How many frames the denoised view is kept for while a render is adding
+/// samples, since denoising takes far longer than drawing a frame.
+const DENOISE_INTERVAL: u32 = 30;
+
+const SCENE_KEYS: [KeyCode; 10] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+    KeyCode::Key0,
+];
+
+pub async fn run(aspect_ratio: f64, options: Options) {
+    let mut canvas = Canvas::default();
+    let framebuffer_ref = Arc::new(Mutex::new(FrameBuffer::new(
+        canvas.width(),
+        canvas.height(),
+    )));
+    let mut scene_index = SCENES
+        .iter()
+        .position(|(name, _)| *name == options.scene)
+        .unwrap_or_default();
+    let mut scene = SCENES[scene_index].1();
+    let mut settings = options.settings;
+    settings.width = canvas.width();
+    settings.height = canvas.height();
+
+    let mut camera_settings = CameraSettings::default();
+    let mut navigator = Navigator::default();
+    let mut camera_moved = false;
+    let mut restart_requested = false;
+    let mut press_position = None;
+    let mut picked: Option<Pick> = None;
+    let mut job = RenderJob::start(
+        framebuffer_ref.clone(),
+        scene.clone(),
+        camera_settings.build(aspect_ratio),
+        settings,
+        JobKind::Full,
+    );
+
+    let font_size = 32;
+
+    let denoiser = Denoiser::default();
+    let mut show_denoised = options.denoise;
+    let mut denoised: Option<Vec<Colour>> = None;
+    let mut frames_since_denoise = 0;
+    let mut show_stats = true;
+
+    let mut threads_running = true;
+    loop {
+        if is_key_pressed(KeyCode::N) {
+            show_denoised = !show_denoised;
+            denoised = None;
+        }
+        if is_key_pressed(KeyCode::Tab) {
+            show_stats = !show_stats;
+        }
+        if is_key_pressed(KeyCode::P) {
+            match job.state() {
+                JobState::Running => job.pause(),
+                JobState::Paused => job.resume(),
+                JobState::Cancelled => {}
+            }
+        }
+        if is_key_pressed(KeyCode::C) {
+            job.cancel();
+        }
+        if is_key_pressed(KeyCode::R) {
+            restart_requested = true;
+        }
+        let mut next_scene = None;
+        for (index, key) in SCENE_KEYS.into_iter().enumerate() {
+            if is_key_pressed(key) && index < SCENES.len() {
+                next_scene = Some(index);
+            }
+        }
+        // There are more scenes than number keys.
+        if is_key_pressed(KeyCode::M) {
+            next_scene = Some((scene_index + 1) % SCENES.len());
+        }
+        if let Some(index) = next_scene {
+            scene_index = index;
+            scene = SCENES[scene_index].1();
+            picked = None;
+            restart_requested = true;
+        }
+        if is_key_pressed(KeyCode::I) {
+            let index = INTEGRATORS
+                .iter()
+                .position(|(_, kind)| *kind == settings.integrator)
+                .unwrap_or_default();
+            settings.integrator = INTEGRATORS[(index + 1) % INTEGRATORS.len()].1;
+            restart_requested = true;
+        }
+        if is_key_pressed(KeyCode::Up) {
+            settings.samples_per_pixel = settings.samples_per_pixel.saturating_mul(2);
+            restart_requested = true;
+        }
+        if is_key_pressed(KeyCode::Down) {
+            settings.samples_per_pixel = (settings.samples_per_pixel / 2).max(1);
+            restart_requested = true;
+        }
+        if is_key_pressed(KeyCode::Right) {
+            settings.max_depth = settings.max_depth.saturating_add(10);
+            restart_requested = true;
+        }
+        if is_key_pressed(KeyCode::Left) {
+            settings.max_depth = settings.max_depth.saturating_sub(10).max(1);
+            restart_requested = true;
+        }
+
+        if is_mouse_button_pressed(MouseButton::Left) {
+            press_position = Some(mouse_position());
+        }
+        if is_mouse_button_released(MouseButton::Left)
+            && press_position.take() == Some(mouse_position())
+        {
+            let (mouse_x, mouse_y) = mouse_position();
+            let u = mouse_x as f64 / (canvas.width() - 1) as f64;
+            let v = (canvas.height() as f64 - 1. - mouse_y as f64) / (canvas.height() - 1) as f64;
+            // Through the centre of the lens, so that clicking the same spot
+            // always picks the same object.
+            picked = pick(
+                scene.world(),
+                &camera_settings
+                    .aperture(0.)
+                    .build(aspect_ratio)
+                    .get_ray(u, v),
+            );
+
+            if let Some(hit) = &picked {
+                if is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift) {
+                    let forward =
+                        (camera_settings.look_at - camera_settings.look_from).unit_vector();
+                    camera_settings.focus_dist = (hit.p - camera_settings.look_from).dot(&forward);
+                    camera_moved = true;
+                }
+            }
+        }
+
+        camera_moved |= navigator.update(&mut camera_settings);
+        let restart = match camera_moved {
+            true => !job.is_preview() || job.is_finished(),
+            false => restart_requested || (job.is_preview() && job.is_finished()),
+        };
+        if restart {
+            job.cancel();
+            job.wait();
+            job = RenderJob::start(
+                framebuffer_ref.clone(),
+                scene.clone(),
+                camera_settings.build(aspect_ratio),
+                settings,
+                match camera_moved {
+                    true => JobKind::Preview,
+                    false => JobKind::Full,
+                },
+            );
+            camera_moved = false;
+            restart_requested = false;
+            threads_running = true;
+            denoised = None;
+        }
+
+        clear_background(WHITE);
+        if show_denoised {
+            frames_since_denoise += 1;
+            if threads_running && frames_since_denoise >= DENOISE_INTERVAL {
+                denoised = None;
+            }
+            // Denoise a copy so the render threads aren't kept waiting.
+            let pixels = denoised.get_or_insert_with(|| {
+                frames_since_denoise = 0;
+                let framebuffer = framebuffer_ref
+                    .lock()
+                    .expect("Something went wrong inside the framebuffer lock")
+                    .clone();
+                denoiser.denoise(&framebuffer)
+            });
+            canvas.update(pixels);
+        } else {
+            let pixels = framebuffer_ref
+                .lock()
+                .expect("Something went wrong inside the framebuffer lock")
+                .pixels();
+            canvas.update(&pixels);
+        }
+        canvas.render();
+        if threads_running && !job.is_preview() {
+            let (status_text, status_colour) = match job.state() {
+                JobState::Running => ("Tracing Rays...", RED),
+                JobState::Paused => ("Paused", BLUE),
+                JobState::Cancelled => ("Cancelling...", DARKGRAY),
+            };
+            let text_dimensions = measure_text(status_text, None, font_size, 1.0);
+            let text_x = (screen_width() - text_dimensions.width) / 2.;
+            let text_y = (screen_height() - text_dimensions.height) / 2.;
+            draw_rectangle(
+                text_x - 8.,
+                text_y - text_dimensions.height / 2. - 16.,
+                text_dimensions.width + 16.,
+                text_dimensions.height + 16.,
+                WHITE,
+            );
+            draw_text(status_text, text_x, text_y, font_size as f32, status_colour);
+        }
+        draw_text(
+            &format!(
+                "{} scene  {}  {}  depth {}  fov {:.0}  aperture {:.2}  focus {:.1}",
+                SCENES[scene_index].0,
+                settings.integrator.name(),
+                match settings.is_adaptive() {
+                    true => "adaptive spp".to_string(),
+                    false => format!("{} spp", settings.samples_per_pixel),
+                },
+                settings.max_depth,
+                camera_settings.v_fov,
+                camera_settings.aperture,
+                camera_settings.focus_dist
+            ),
+            8.,
+            screen_height() - 8.,
+            20.,
+            WHITE,
+        );
+        if let Some(hit) = &picked {
+            draw_text(&hit.to_string(), 8., 20., 20., WHITE);
+        }
+        if show_stats {
+            for (line_number, line) in job.stats().lines().iter().enumerate() {
+                draw_text(line, 8., 44. + 20. * line_number as f32, 20., WHITE);
+            }
+        }
+        next_frame().await;
+        if threads_running && job.is_finished() && !job.is_preview() {
+            job.stats().finish();
+            threads_running = false;
+            denoised = None;
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use std::io::{Error, ErrorKind, Read, Result, Write};
+
+use crate::prelude::*;
+
+/// The longest string either end will accept, so that a corrupt length can't
+/// make the reader allocate gigabytes.
+const MAX_STRING_LENGTH: u32 = 4096;
+
+pub fn write_u8(out: &mut impl Write, value: u8) -> Result<()> {
+    out.write_all(&[value])
+}
+
+pub fn write_u32(out: &mut impl Write, value: u32) -> Result<()> {
+    out.write_all(&value.to_le_bytes())
+}
+
+pub fn write_u64(out: &mut impl Write, value: u64) -> Result<()> {
+    out.write_all(&value.to_le_bytes())
+}
+
+pub fn write_f64(out: &mut impl Write, value: f64) -> Result<()> {
+    out.write_all(&value.to_le_bytes())
+}
+
+pub fn write_vector(out: &mut impl Write, value: &Vector3) -> Result<()> {
+    write_f64(out, value.x())?;
+    write_f64(out, value.y())?;
+    write_f64(out, value.z())
+}
+
+pub fn write_string(out: &mut impl Write, value: &str) -> Result<()> {
+    write_u32(out, value.len() as u32)?;
+    out.write_all(value.as_bytes())
+}
+
+pub fn read_u8(input: &mut impl Read) -> Result<u8> {
+    let mut bytes = [0u8; 1];
+    input.read_exact(&mut bytes)?;
+    Ok(bytes[0])
+}
+
+pub fn read_u32(input: &mut impl Read) -> Result<u32> {
+    let mut bytes = [0u8; 4];
+    input.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+pub fn read_u64(input: &mut impl Read) -> Result<u64> {
+    let mut bytes = [0u8; 8];
+    input.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+pub fn read_f64(input: &mut impl Read) -> Result<f64> {
+    let mut bytes = [0u8; 8];
+    input.read_exact(&mut bytes)?;
+    Ok(f64::from_le_bytes(bytes))
+}
+
+pub fn read_vector(input: &mut impl Read) -> Result<Vector3> {
+    Ok(Vector3::new(
+        read_f64(input)?,
+        read_f64(input)?,
+        read_f64(input)?,
+    ))
+}
+
+pub fn read_string(input: &mut impl Read) -> Result<String> {
+    let length = read_u32(input)?;
+    if length > MAX_STRING_LENGTH {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("String of {} bytes is too long", length),
+        ));
+    }
+    let mut bytes = vec![0u8; length as usize];
+    input.read_exact(&mut bytes)?;
+    String::from_utf8(bytes).map_err(|error| Error::new(ErrorKind::InvalidData, error))
+}